@@ -0,0 +1,160 @@
+// Compares a library directory against its library file
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::{metadata, read_dir};
+use std::io::Error;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::cover::find_cover;
+use crate::probe::{get_decode_errors, get_duration};
+use crate::tagger::{get_expected_tags, read_tags};
+use crate::{duration_seconds_parse, get_path_album, get_path_track, Album, Config, Library, AUDIO_FORMATS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    MissingAlbum,
+    MissingTrack,
+    ExtraFile,
+    EmptyFile,
+    Undecodable,
+    TagMismatch,
+    MissingArtwork,
+    Duration,
+}
+
+impl Display for IssueKind {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let kind = match self {
+            IssueKind::MissingAlbum => "missing album",
+            IssueKind::MissingTrack => "missing track",
+            IssueKind::ExtraFile => "extra file",
+            IssueKind::EmptyFile => "empty file",
+            IssueKind::Undecodable => "undecodable",
+            IssueKind::TagMismatch => "tag mismatch",
+            IssueKind::MissingArtwork => "missing artwork",
+            IssueKind::Duration => "duration",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// Something in the library directory that doesn't match the library file.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub path: String,
+    pub detail: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        match self.detail.is_empty() {
+            true => write!(f, "{}: {}", self.kind, self.path),
+            false => write!(f, "{}: {}: {}", self.kind, self.path, self.detail),
+        }
+    }
+}
+
+fn make_issue(kind: IssueKind, path: &str, detail: impl Into<String>) -> Issue {
+    Issue { kind, path: path.to_owned(), detail: detail.into() }
+}
+
+/// Audits every album of a library.
+///
+/// Files that can't be checked are reported as issues, the audit goes on with the next one.
+/// Durations are reported when they are off by more than `tolerance` seconds.
+pub fn audit_library(config: &Config, path_library: &str, library: &Library, tolerance: f64) -> Result<Vec<Issue>, Error> {
+    let mut issues = vec![];
+    for album in &library.albums {
+        issues.extend(audit_album(config, path_library, album, tolerance)?);
+    }
+    Ok(issues)
+}
+
+fn audit_album(config: &Config, path_library: &str, album: &Album, tolerance: f64) -> Result<Vec<Issue>, Error> {
+    let path_album = get_path_album(path_library, album);
+    if !Path::new(&path_album).exists() {
+        return Ok(vec![make_issue(IssueKind::MissingAlbum, &path_album, "")]);
+    }
+    if config.debug {
+        println!("{}", path_album);
+    }
+
+    let mut issues = vec![];
+    if find_cover(&path_album).is_none() {
+        issues.push(make_issue(IssueKind::MissingArtwork, &path_album, "no folder image"));
+    }
+
+    let mut expected = HashSet::new();
+    for (track_position, track) in album.tracks.iter() {
+        let path_track = get_path_track(&path_album, track_position, track, config.audio_fmt);
+        expected.insert(path_track.clone());
+        if !Path::new(&path_track).exists() {
+            issues.push(make_issue(IssueKind::MissingTrack, &path_track, ""));
+            continue;
+        }
+        if metadata(&path_track)?.len() == 0 {
+            issues.push(make_issue(IssueKind::EmptyFile, &path_track, ""));
+            continue;
+        }
+        match get_decode_errors(&path_track) {
+            Ok(None) => {}
+            Ok(Some(errors)) => {
+                issues.push(make_issue(IssueKind::Undecodable, &path_track, errors));
+                continue;
+            }
+            Err(err) => {
+                issues.push(make_issue(IssueKind::Undecodable, &path_track, err.to_string()));
+                continue;
+            }
+        }
+
+        match read_tags(&path_track) {
+            Ok(tags) => {
+                let diff = tags.diff(&get_expected_tags(album, track, track_position));
+                if !diff.is_empty() {
+                    issues.push(make_issue(IssueKind::TagMismatch, &path_track, diff.join(", ")));
+                }
+                if tags.cover.is_empty() {
+                    issues.push(make_issue(IssueKind::MissingArtwork, &path_track, "no embedded cover"));
+                }
+            }
+            Err(err) => issues.push(make_issue(IssueKind::TagMismatch, &path_track, err.to_string())),
+        }
+
+        if let Some(duration) = &track.duration {
+            let expected = match duration_seconds_parse(duration) {
+                Ok(expected) => expected as f64,
+                Err(err) => {
+                    let detail = format!("invalid duration {} in the library: {}", duration, err);
+                    issues.push(make_issue(IssueKind::Duration, &path_track, detail));
+                    continue;
+                }
+            };
+            let actual = match get_duration(&path_track) {
+                Ok(actual) => actual,
+                Err(err) => {
+                    issues.push(make_issue(IssueKind::Duration, &path_track, format!("could not read: {}", err)));
+                    continue;
+                }
+            };
+            if (actual - expected).abs() > tolerance {
+                let detail = format!("{:.0}s, expected {}s", actual, expected);
+                issues.push(make_issue(IssueKind::Duration, &path_track, detail));
+            }
+        }
+    }
+
+    for entry in read_dir(&path_album)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+        let path = path.to_string_lossy().to_string();
+        if AUDIO_FORMATS.contains(&extension.as_str()) && !expected.contains(&path) {
+            issues.push(make_issue(IssueKind::ExtraFile, &path, ""));
+        }
+    }
+    Ok(issues)
+}
@@ -0,0 +1,220 @@
+// Downloads shared between albums and syncs, so a reused upload is only downloaded once
+use std::collections::BTreeMap;
+use std::fs::{copy, create_dir_all, read, read_dir, remove_file, File};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Size the cache is kept under by default, in MB.
+pub const CACHE_SIZE: u64 = 10 * 1024;
+
+const FILE_INDEX: &str = "index.yml";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct CacheIndex {
+    /// Entries by their key, see [`get_cache_key`].
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CacheEntry {
+    pub url: String,
+    pub format: String,
+    /// Size of the file in bytes.
+    pub size: u64,
+    /// SHA-256 of the file, to verify it.
+    pub sha256: String,
+    /// Unix timestamp of when the entry was last used, the oldest is evicted first.
+    pub used: i64,
+}
+
+pub struct Cache {
+    path: String,
+    /// Largest total size of the files, in bytes.
+    limit: u64,
+    index: CacheIndex,
+}
+
+/// Returns the default cache directory, `$XDG_CACHE_HOME/yar`.
+pub fn get_path_cache() -> String {
+    let path_cache = match std::env::var("XDG_CACHE_HOME") {
+        Ok(path) if !path.is_empty() => path,
+        _ => format!("{}/.cache", std::env::var("HOME").unwrap_or_default()),
+    };
+    format!("{}/yar", path_cache)
+}
+
+/// Returns the key of a download, the same location in another format is another file.
+///
+/// ```
+/// use yar::cache::get_cache_key;
+/// let key = get_cache_key("https://youtu.be/a", "mp3");
+/// assert_eq!(key.len(), 32);
+/// assert_eq!(key, get_cache_key("https://youtu.be/a", "mp3"));
+/// assert_ne!(key, get_cache_key("https://youtu.be/a", "flac"));
+/// ```
+pub fn get_cache_key(url: &str, format: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(format!("{}\n{}", url, format).as_bytes()));
+    hash[..32].to_owned()
+}
+
+fn get_sha256(path: &str) -> Result<String, Error> {
+    Ok(format!("{:x}", Sha256::digest(read(path)?)))
+}
+
+fn get_now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl Cache {
+    /// Opens a cache directory, creating it if needed. `limit` is in MB.
+    pub fn open(path: &str, limit: u64) -> Result<Cache, Error> {
+        create_dir_all(path)?;
+        let path_index = format!("{}/{}", path, FILE_INDEX);
+        let index = match File::open(&path_index) {
+            Ok(file) => match serde_yaml::from_reader(file) {
+                Ok(index) => index,
+                Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("Could not read cache index {}: {}", path_index, err))),
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => CacheIndex::default(),
+            Err(err) => return Err(err),
+        };
+        Ok(Cache {
+            path: path.to_owned(),
+            limit: limit * 1024 * 1024,
+            index,
+        })
+    }
+
+    pub fn entries(&self) -> &BTreeMap<String, CacheEntry> {
+        &self.index.entries
+    }
+
+    /// Total size of the cached files, in bytes.
+    pub fn size(&self) -> u64 {
+        self.index.entries.values().map(|entry| entry.size).sum()
+    }
+
+    pub fn get_path_entry(&self, key: &str, entry: &CacheEntry) -> String {
+        format!("{}/{}.{}", self.path, key, entry.format)
+    }
+
+    /// Copies a cached download to `path_out`, returns false if it isn't cached.
+    pub fn get(&mut self, url: &str, format: &str, path_out: &str) -> Result<bool, Error> {
+        let key = get_cache_key(url, format);
+        let path_entry = match self.index.entries.get(&key) {
+            Some(entry) => self.get_path_entry(&key, entry),
+            None => return Ok(false),
+        };
+        if let Err(err) = copy(&path_entry, path_out) {
+            // Removed from outside, eg. by hand
+            if err.kind() == ErrorKind::NotFound {
+                self.index.entries.remove(&key);
+                self.write_index()?;
+                return Ok(false);
+            }
+            return Err(err);
+        }
+        if let Some(entry) = self.index.entries.get_mut(&key) {
+            entry.used = get_now();
+        }
+        self.write_index()?;
+        Ok(true)
+    }
+
+    /// Adds a download to the cache and evicts the least recently used ones over the limit.
+    ///
+    /// Files larger than the whole cache are not added.
+    pub fn insert(&mut self, url: &str, format: &str, path: &str) -> Result<(), Error> {
+        let size = std::fs::metadata(path)?.len();
+        if size > self.limit {
+            return Ok(());
+        }
+        let key = get_cache_key(url, format);
+        let entry = CacheEntry {
+            url: url.to_owned(),
+            format: format.to_owned(),
+            size,
+            sha256: get_sha256(path)?,
+            used: get_now(),
+        };
+        copy(path, self.get_path_entry(&key, &entry))?;
+        self.index.entries.insert(key.clone(), entry);
+        self.evict(&key)?;
+        self.write_index()
+    }
+
+    fn evict(&mut self, key_kept: &str) -> Result<(), Error> {
+        while self.size() > self.limit {
+            let oldest = self
+                .index
+                .entries
+                .iter()
+                .filter(|(key, _)| key.as_str() != key_kept)
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.to_owned());
+            match oldest {
+                Some(key) => self.remove(&key)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) -> Result<(), Error> {
+        if let Some(entry) = self.index.entries.remove(key) {
+            match remove_file(self.get_path_entry(key, &entry)) {
+                Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the cached files against the index and removes the entries that are broken.
+    ///
+    /// Returns the URL of each removed entry with what was wrong.
+    pub fn verify(&mut self) -> Result<Vec<(String, String)>, Error> {
+        let mut broken = vec![];
+        for (key, entry) in self.index.entries.iter() {
+            let path = self.get_path_entry(key, entry);
+            let problem = match std::fs::metadata(&path) {
+                Err(_) => Some(String::from("file is missing")),
+                Ok(metadata) if metadata.len() != entry.size => Some(format!("size is {}, expected {}", metadata.len(), entry.size)),
+                Ok(_) if get_sha256(&path)? != entry.sha256 => Some(String::from("checksum does not match")),
+                Ok(_) => None,
+            };
+            if let Some(problem) = problem {
+                broken.push((key.to_owned(), entry.url.to_owned(), problem));
+            }
+        }
+        for (key, _, _) in broken.iter() {
+            self.remove(key)?;
+        }
+        self.write_index()?;
+        Ok(broken.into_iter().map(|(_, url, problem)| (url, problem)).collect())
+    }
+
+    /// Removes every cached file.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.index.entries.clear();
+        for file in read_dir(&self.path)? {
+            let path = file?.path();
+            if path.is_file() {
+                remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_index(&self) -> Result<(), Error> {
+        let path_index = format!("{}/{}", self.path, FILE_INDEX);
+        let file = File::create(Path::new(&path_index))?;
+        match serde_yaml::to_writer(file, &self.index) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(Error::new(ErrorKind::InvalidData, "Could not serialize cache index")),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+// Settings from `~/.config/yar/config.yml`, overridden by environment variables
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+use crate::{Config, LibrarySource, AUDIO_FORMATS};
+
+/// Prefix of the environment variables that override settings, eg. `YAR_FORMAT=flac`.
+pub const ENV_PREFIX: &str = "YAR_";
+
+/// Everything that can be set in the config file.
+///
+/// Missing keys keep their default and unknown keys are ignored, so a config file
+/// written for a newer version still works.
+///
+/// There is no `jobs` setting, albums and their tracks are synced one at a time.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Path to the library structure file.
+    pub file: Option<String>,
+    /// Path to a db directory of album files, used when there is no library file.
+    pub db: Option<String>,
+    /// Path to the directory of the library.
+    pub dir: Option<String>,
+    /// Audio format of the library.
+    pub format: String,
+    pub debug: bool,
+    pub debug_ytdl: bool,
+    pub debug_ffmpeg: bool,
+    pub force: bool,
+    pub download_covers: bool,
+    pub keep_full_files: bool,
+    pub replaygain: bool,
+    pub cover_size: Option<u32>,
+    pub retag: bool,
+    pub strip_tags: bool,
+    /// See [`Config::duration_tolerance`], `null` skips the check.
+    pub duration_tolerance: Option<f64>,
+    pub fingerprint: bool,
+    /// See [`Config::cache`], `null` turns it off.
+    pub cache: Option<String>,
+    pub cache_size: u64,
+    /// How often a failed download is tried again.
+    pub retries: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        let config = Config::default();
+        Settings {
+            file: None,
+            db: None,
+            dir: None,
+            format: config.audio_fmt.to_owned(),
+            debug: config.debug,
+            debug_ytdl: config.debug_ytdl,
+            debug_ffmpeg: config.debug_ffmpeg,
+            force: config.force,
+            download_covers: config.download_covers,
+            keep_full_files: config.keep_full_files,
+            replaygain: config.replaygain,
+            cover_size: config.cover_size,
+            retag: config.retag,
+            strip_tags: config.strip_tags,
+            duration_tolerance: config.duration_tolerance,
+            fingerprint: config.fingerprint,
+            cache: config.cache,
+            cache_size: config.cache_size,
+            retries: config.retries,
+        }
+    }
+}
+
+impl Settings {
+    pub fn to_config(&self) -> Config<'_> {
+        Config {
+            debug_ytdl: self.debug_ytdl,
+            debug_ffmpeg: self.debug_ffmpeg,
+            debug: self.debug,
+            audio_fmt: &self.format,
+            force: self.force,
+            download_covers: self.download_covers,
+            keep_full_files: self.keep_full_files,
+            replaygain: self.replaygain,
+            cover_size: self.cover_size,
+            retag: self.retag,
+            strip_tags: self.strip_tags,
+            duration_tolerance: self.duration_tolerance,
+            fingerprint: self.fingerprint,
+            cache: self.cache.clone(),
+            cache_size: self.cache_size,
+            retries: self.retries,
+        }
+    }
+}
+
+impl Settings {
+    /// Returns where the library is read from, the library file is used over the db directory.
+    pub fn get_library_source(&self) -> Option<LibrarySource> {
+        match (&self.file, &self.db) {
+            (Some(path_file), _) => Some(LibrarySource::File(path_file.to_owned())),
+            (None, Some(path_db)) => Some(LibrarySource::Db(path_db.to_owned())),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Returns the directory of yar's config files, `$XDG_CONFIG_HOME/yar`.
+pub fn get_path_config_dir() -> String {
+    let path_config = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(path) if !path.is_empty() => path,
+        _ => format!("{}/.config", std::env::var("HOME").unwrap_or_default()),
+    };
+    format!("{}/yar", path_config)
+}
+
+/// Returns the default path of the config file, `~/.config/yar/config.yml`.
+pub fn get_path_config() -> String {
+    format!("{}/config.yml", get_path_config_dir())
+}
+
+/// Reads the settings from a config file and the environment.
+///
+/// A missing file is only an error when its path was given, and so is an unknown format.
+pub fn read_settings(path: Option<&str>) -> Result<Settings, Error> {
+    read_settings_with_env(path, std::env::vars())
+}
+
+/// Reads the settings like [`read_settings`], with the given environment variables.
+pub fn read_settings_with_env(path: Option<&str>, vars: impl IntoIterator<Item = (String, String)>) -> Result<Settings, Error> {
+    let mut values = match path {
+        Some(path) => read_config(path)?,
+        None => {
+            let path = get_path_config();
+            match std::path::Path::new(&path).exists() {
+                true => read_config(&path)?,
+                false => Mapping::new(),
+            }
+        }
+    };
+    apply_env(&mut values, vars)?;
+    let settings: Settings = match serde_yaml::from_value(Value::Mapping(values)) {
+        Ok(settings) => settings,
+        Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("Invalid settings: {}", err))),
+    };
+    if !AUDIO_FORMATS.contains(&settings.format.as_str()) {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid settings: format {} is not one of {}", settings.format, AUDIO_FORMATS.join(", ")),
+        ));
+    }
+    Ok(settings)
+}
+
+fn read_config(path: &str) -> Result<Mapping, Error> {
+    let file = File::open(path)?;
+    match serde_yaml::from_reader::<_, Option<Mapping>>(file) {
+        Ok(values) => Ok(values.unwrap_or_default()),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("Could not read config {}: {}", path, err))),
+    }
+}
+
+/// Sets the values of environment variables named after a setting, eg. `YAR_COVER_SIZE=600`.
+///
+/// ```
+/// use serde_yaml::{Mapping, Value};
+/// use yar::config::apply_env;
+/// let mut values = Mapping::new();
+/// let vars = vec![
+///     (String::from("YAR_FORMAT"), String::from("flac")),
+///     (String::from("YAR_COVER_SIZE"), String::from("600")),
+///     (String::from("YAR_UNKNOWN"), String::from("1")),
+/// ];
+/// apply_env(&mut values, vars).unwrap();
+/// assert_eq!(values.get(&Value::from("format")), Some(&Value::from("flac")));
+/// assert_eq!(values.get(&Value::from("cover_size")), Some(&Value::from(600)));
+/// assert_eq!(values.len(), 2);
+/// ```
+pub fn apply_env(values: &mut Mapping, vars: impl IntoIterator<Item = (String, String)>) -> Result<(), Error> {
+    let keys = get_keys();
+    for (name, value) in vars {
+        let key = match name.strip_prefix(ENV_PREFIX) {
+            Some(key) => key.to_lowercase(),
+            None => continue,
+        };
+        if !keys.contains(&key) {
+            continue;
+        }
+        // Parsed as YAML so numbers, booleans and null have their type
+        let value: Value = match serde_yaml::from_str(&value) {
+            Ok(value) => value,
+            Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("Invalid value of {}: {}", name, err))),
+        };
+        values.insert(Value::from(key), value);
+    }
+    Ok(())
+}
+
+/// Returns the keys of every setting.
+fn get_keys() -> Vec<String> {
+    match serde_yaml::to_value(Settings::default()) {
+        Ok(Value::Mapping(values)) => values
+            .iter()
+            .filter_map(|(key, _)| key.as_str())
+            .map(String::from)
+            .collect(),
+        _ => vec![],
+    }
+}
@@ -0,0 +1,158 @@
+// Album artwork: the folder image is kept as downloaded, tracks embed a JPEG copy
+use std::fs::{read, read_to_string, remove_file, write, File};
+use std::io::{Cursor, Error, ErrorKind, Write};
+use std::path::Path;
+
+use image::imageops::FilterType;
+use image::{guess_format, ImageFormat, ImageOutputFormat};
+
+use crate::{Album, Config};
+
+/// Quality of the JPEG that is embedded into tracks.
+const JPEG_QUALITY: u8 = 90;
+
+/// Returns the extension of an image by looking at its bytes, not the URL it came from.
+///
+/// # Example
+/// ```
+/// let png = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+/// assert_eq!(yar::cover::get_cover_extension(&png), "png");
+/// ```
+pub fn get_cover_extension(bytes: &[u8]) -> &'static str {
+    match guess_format(bytes) {
+        Ok(ImageFormat::Png) => "png",
+        Ok(ImageFormat::WebP) => "webp",
+        Ok(ImageFormat::Gif) => "gif",
+        _ => "jpg",
+    }
+}
+
+/// Extensions the folder image can be saved with.
+const COVER_EXTENSIONS: [&str; 4] = ["jpg", "png", "webp", "gif"];
+
+/// Finds the folder image of an album directory, eg. `cover.png`.
+pub fn find_cover(path_album: &str) -> Option<String> {
+    COVER_EXTENSIONS
+        .iter()
+        .map(|extension| format!("{}cover.{}", path_album, extension))
+        .find(|path| Path::new(path).exists())
+}
+
+/// Returns the folder image of an album without downloading anything, empty if there is none.
+pub fn read_cover(path_album: &str) -> Result<Vec<u8>, Error> {
+    match find_cover(path_album) {
+        Some(path_cover) => read(path_cover),
+        None => Ok(vec![]),
+    }
+}
+
+/// Returns the path of the file that stores which URL the folder image was downloaded from.
+fn get_path_cover_url(path_album: &str) -> String {
+    format!("{}cover.url", path_album)
+}
+
+/// Checks if the folder image was downloaded from the album's current cover URL.
+fn is_cover_current(path_album: &str, album: &Album) -> bool {
+    match read_to_string(get_path_cover_url(path_album)) {
+        Ok(url) => url.trim() == album.cover,
+        Err(_) => false,
+    }
+}
+
+/// Returns the cover of an album, read from the folder image when it is up to date.
+///
+/// Otherwise the cover is downloaded and saved as the folder image. When the download fails
+/// the existing folder image is used, even if it is out of date. Without `download_covers`
+/// a folder image that is already there is always used, as nothing would update it.
+/// An album without a cover URL only has the folder image, if there is one.
+pub fn get_cover(config: &Config, path_album: &str, album: &Album) -> Result<Vec<u8>, Error> {
+    let path_cover = find_cover(path_album);
+    if let Some(path_cover) = &path_cover {
+        let is_current = !config.download_covers || is_cover_current(path_album, album);
+        if !config.force && is_current {
+            if config.debug {
+                println!("Using cached cover: {}", path_cover)
+            }
+            match read(path_cover) {
+                Ok(bytes) => return Ok(bytes),
+                Err(err) => {
+                    println!("ERR: Could not read cover: {}", path_cover);
+                    println!("{}", err);
+                }
+            }
+        }
+    }
+
+    let cover_url = &album.cover;
+    // Albums without a cover, eg. imported from files without one
+    if cover_url.is_empty() {
+        return match &path_cover {
+            Some(path_cover) => read(path_cover),
+            None => Ok(vec![]),
+        };
+    }
+    if config.debug {
+        println!("downloading cover")
+    }
+    let bytes = match (fetch_cover(cover_url), &path_cover) {
+        (Ok(bytes), _) => bytes,
+        (Err(err), Some(path_cover)) => {
+            println!("ERR: Could not download cover, using {}", path_cover);
+            println!("{}", err);
+            return read(path_cover);
+        }
+        (Err(err), None) => return Err(err),
+    };
+    if config.download_covers {
+        if let Some(path_cover) = &path_cover {
+            remove_file(path_cover)?;
+        }
+        let path_cover = format!("{}cover.{}", path_album, get_cover_extension(&bytes));
+        write_cover(&path_cover, &bytes)?;
+        write(get_path_cover_url(path_album), cover_url)?;
+    }
+    Ok(bytes)
+}
+
+/// Loads a cover from a URL, `file://` URLs are read from disk.
+fn fetch_cover(url: &str) -> Result<Vec<u8>, Error> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return read(path);
+    }
+    let response = reqwest::blocking::get(url).map_err(Error::other)?;
+    Ok(response.bytes().map_err(Error::other)?.to_vec())
+}
+
+fn write_cover(path: &str, image_bytes: &[u8]) -> Result<(), Error> {
+    let mut file = File::create(path)?;
+    file.write_all(image_bytes)?;
+    Ok(())
+}
+
+/// Returns the cover as a JPEG that fits in `max_size` pixels, for embedding into tracks.
+///
+/// JPEGs that are small enough are returned as they are.
+pub fn make_embedded_cover(bytes: &[u8], max_size: Option<u32>) -> Result<Vec<u8>, Error> {
+    if bytes.is_empty() {
+        return Ok(vec![]);
+    }
+    let format = guess_format(bytes).map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+    let image = image::load_from_memory_with_format(bytes, format)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+
+    let is_too_large = max_size.is_some_and(|max_size| image.width() > max_size || image.height() > max_size);
+    if format == ImageFormat::Jpeg && !is_too_large {
+        return Ok(bytes.to_vec());
+    }
+    let image = match max_size {
+        // Keeps the aspect ratio
+        Some(max_size) if is_too_large => image.resize(max_size, max_size, FilterType::Lanczos3),
+        _ => image,
+    };
+
+    let mut jpeg = Cursor::new(vec![]);
+    match image.to_rgb8().write_to(&mut jpeg, ImageOutputFormat::Jpeg(JPEG_QUALITY)) {
+        Ok(_) => Ok(jpeg.into_inner()),
+        Err(err) => Err(Error::other(err.to_string())),
+    }
+}
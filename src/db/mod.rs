@@ -0,0 +1,92 @@
+// Reads a library from a directory of album files, eg. the phonkhub db repo
+use std::fs::{read_dir, DirEntry, File};
+use std::io::{Error, ErrorKind};
+use std::iter::once;
+use std::path::Path;
+
+use crate::{Album, Library};
+
+/// Albums read one at a time, so a sync can start before the whole db is read.
+pub type Albums = Box<dyn Iterator<Item = Result<Album, Error>>>;
+
+/// Reads the albums of a db directory and the repos in its `repo/` directory.
+///
+/// Album files are at `artists/<artist>/<album>.yml`, see [`crate::importer::get_path_album_file`].
+pub fn scan_db(path_in: &str) -> Result<Library, Error> {
+    let albums = read_db_albums(path_in)?.collect::<Result<Vec<Album>, Error>>()?;
+    Ok(Library { albums })
+}
+
+/// Returns the albums of a db directory as they are read, see [`scan_db`].
+pub fn read_db_albums(path_in: &str) -> Result<Albums, Error> {
+    let path_artists = &get_path_artists(path_in);
+    if !Path::new(path_artists).is_dir() {
+        return Err(Error::new(ErrorKind::NotFound, format!("no path at: {}", path_artists)));
+    }
+    let mut paths = vec![path_in.to_owned()];
+
+    let path_repos = format!("{}/repo/", path_in);
+    if let Ok(repos) = read_dir(path_repos) {
+        for entry in repos {
+            let path_repo = entry?;
+            if !is_visible_dir(&path_repo) { continue; }
+            paths.push(path_repo.path().to_string_lossy().to_string());
+        }
+    }
+    Ok(Box::new(paths.into_iter().flat_map(|path| read_artists(&path))))
+}
+
+/// Reads the albums in the `artists/` directory of a db or one of its repos.
+pub fn scan_artists(path: &str, library: &mut Library) -> Result<(), Error> {
+    for album in read_artists(path) {
+        library.albums.push(album?);
+    }
+    Ok(())
+}
+
+fn read_artists(path: &str) -> Albums {
+    let albums = read_entries(&get_path_artists(path))
+        .filter(|entry| entry.as_ref().map_or(true, is_visible_dir))
+        .flat_map(|entry| match entry {
+            Ok(path_artist) => read_entries(&path_artist.path().to_string_lossy()),
+            Err(err) => Box::new(once(Err(err))),
+        })
+        .filter(|entry| entry.as_ref().map_or(true, is_visible_file))
+        .map(|entry| entry.and_then(|file| read_album_file(&file)));
+    Box::new(albums)
+}
+
+fn get_path_artists(path: &str) -> String {
+    format!("{}/artists/", path)
+}
+
+/// Lists a directory, a directory that can't be read is a single error.
+fn read_entries(path: &str) -> Box<dyn Iterator<Item = Result<DirEntry, Error>>> {
+    match read_dir(path) {
+        Ok(entries) => Box::new(entries),
+        Err(err) => Box::new(once(Err(Error::new(err.kind(), format!("no path at: {}", path))))),
+    }
+}
+
+fn read_album_file(file: &DirEntry) -> Result<Album, Error> {
+    let f = File::open(file.path())?;
+    match serde_yaml::from_reader(f) {
+        Ok(album) => Ok(album),
+        Err(err) => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("could not read vals: {}: {}", file.path().display(), err),
+        )),
+    }
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
+
+fn is_visible_dir(entry: &DirEntry) -> bool {
+    entry.file_type().is_ok_and(|file_type| file_type.is_dir()) && !is_hidden(entry)
+}
+
+fn is_visible_file(entry: &DirEntry) -> bool {
+    entry.file_type().is_ok_and(|file_type| !file_type.is_dir()) && !is_hidden(entry)
+}
@@ -1,14 +1,19 @@
 mod util;
-use util::{get_end_time, has_full};
+use util::{get_end_time, get_path_full, has_full};
 
 mod splitter;
 use splitter::split_track;
 
-use std::fs::File;
-use std::io::{Error, ErrorKind, Write};
+use std::fs::remove_file;
+use std::io::{Error, ErrorKind};
 use std::process::Command;
-use crate::{get_stdout, Album, Config, Location, Track};
+use crate::cache::Cache;
+use crate::probe::get_duration;
+use crate::{duration_seconds_parse, get_stdout, Album, Config, Location, Track};
 
+/// Downloads a track from the first of its locations that works.
+///
+/// Returns the location it was downloaded from, or `None` if the file already existed.
 pub fn download_track(
     config: &Config,
     full_files: &mut Vec<String>,
@@ -16,36 +21,53 @@ pub fn download_track(
     path_out: &str,
     album: &Album,
     track: &Track,
-    track_pos_str: &str,
-) -> Result<(), Error> {
+) -> Result<Option<Location>, Error> {
     let should_download = !std::path::Path::new(path_out).exists() || config.force;
     if !should_download {
         if config.debug {
             println!("Skipping: {}", path_out)
         }
-        return Ok(());
+        return Ok(None);
     }
+    let duration = match &track.duration {
+        Some(duration) => Some(duration_seconds_parse(duration)?),
+        None => None,
+    };
     for location in track.location.iter() {
         match &location.at {
             Some(start) => {
-                let path_full = &format!("{}full.mp3", out_dir);
+                let path_full = &get_path_full(out_dir, &location.url, config.audio_fmt);
                 match download_full(config, full_files, path_full, location) {
                     Ok(_) => {
-                        let end = match get_end_time(album, track, track_pos_str) {
-                            Ok(end) => Some(end),
-                            Err(_) => None,
+                        let end = get_end_time(album, track, location)?;
+                        // Without a duration the track should be as long as the gap to the next one
+                        let expected = match (duration, &end) {
+                            (Some(duration), _) => Some(duration),
+                            (None, Some(end)) => Some(duration_seconds_parse(end)? - duration_seconds_parse(start)?),
+                            (None, None) => None,
                         };
-                        return split_track(config, path_full, path_out, start, end);
+                        split_track(config, path_full, path_out, start, end)?;
+                        if let Err(err) = check_duration(config, path_out, expected) {
+                            let _ = remove_file(path_out);
+                            return Err(err);
+                        }
+                        return Ok(Some(location.clone()));
                     }
                     Err(err) => {
                         println!("Error: URL failed");
                         println!("{}", err);
-                        return Err(err);
                     }
                 }
             }
             None => match download_track_at_location(config, path_out, location) {
-                Ok(_) => return Ok(()),
+                Ok(_) => match check_duration(config, path_out, duration) {
+                    Ok(_) => return Ok(Some(location.clone())),
+                    // eg. a preview, the next location might have the whole track
+                    Err(err) => {
+                        println!("Error: {}", err);
+                        let _ = remove_file(path_out);
+                    }
+                },
                 Err(err) => {
                     println!("Error: URL failed");
                     println!("{}", err);
@@ -56,15 +78,44 @@ pub fn download_track(
     Result::Err(Error::new(ErrorKind::NotFound, "All locations failed ;("))
 }
 
+/// Checks that a file is about as long as expected, in seconds.
+fn check_duration(config: &Config, path: &str, expected: Option<i32>) -> Result<(), Error> {
+    let (expected, tolerance) = match (expected, config.duration_tolerance) {
+        (Some(expected), Some(tolerance)) => (expected as f64, tolerance),
+        _ => return Ok(()),
+    };
+    let actual = match get_duration(path) {
+        Ok(actual) => actual,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            println!("ERR: Could not check duration, is ffprobe installed?");
+            return Ok(());
+        }
+        Err(err) => return Err(err),
+    };
+    if config.debug {
+        println!("duration: {:.0}s, expected {}s", actual, expected);
+    }
+    if (actual - expected).abs() > tolerance {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("{} is {:.0}s long, expected {}s", path, actual, expected),
+        ));
+    }
+    Ok(())
+}
+
 fn download_full(
     config: &Config,
     full_files: &mut Vec<String>,
     path_full: &str,
     location: &Location,
 ) -> Result<(), Error> {
-    if !has_full(&path_full, full_files) {
+    if !has_full(path_full, full_files) {
         if config.debug {
-            println!("Downloading full file: {}", path_full)
+            println!("Downloading full file: {} ({})", path_full, location.url)
+        }
+        if let Some(parent) = std::path::Path::new(path_full).parent() {
+            std::fs::create_dir_all(parent)?;
         }
         match download_track_at_location(config, path_full, location) {
             Ok(_) => {
@@ -86,60 +137,91 @@ fn download_track_at_location(
     path_out: &str,
     location: &Location,
 ) -> Result<(), Error> {
+    let mut cache = open_cache(config, &location.url);
+    if let Some(cache) = cache.as_mut() {
+        match cache.get(&location.url, config.audio_fmt, path_out) {
+            Ok(true) => {
+                if config.debug {
+                    println!("Using cached download of {}", location.url)
+                }
+                return Ok(());
+            }
+            Ok(false) => {}
+            Err(err) => {
+                println!("ERR: Could not read from cache");
+                println!("{}", err);
+            }
+        }
+    }
+
     let args = get_downloader_args(&location.url, config.audio_fmt, path_out);
-    let stdout = get_stdout(config.debug_ytdl);
-    let result = Command::new("yt-dlp")
-        .args(args)
-        .stdout(stdout)
-        .output();
-    match result {
-        Ok(_) => Ok(()),
-        Err(err) => Err(err),
+    let mut attempt = 0;
+    loop {
+        let stdout = get_stdout(config.debug_ytdl);
+        let output = Command::new("yt-dlp")
+            .args(&args)
+            .stdout(stdout)
+            .output()?;
+        if output.status.success() {
+            break;
+        }
+        if attempt >= config.retries {
+            return Err(Error::other(format!(
+                "yt-dlp failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        attempt += 1;
+        println!("Download failed, retrying ({}/{})", attempt, config.retries);
+    }
+
+    if let Some(cache) = cache.as_mut() {
+        if std::path::Path::new(path_out).exists() {
+            if let Err(err) = cache.insert(&location.url, config.audio_fmt, path_out) {
+                println!("ERR: Could not add download to cache");
+                println!("{}", err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Opens the download cache, `None` if it's turned off or the URL is a local file.
+fn open_cache(config: &Config, url: &str) -> Option<Cache> {
+    let path_cache = config.cache.as_ref()?;
+    if url.starts_with("file://") {
+        return None;
+    }
+    match Cache::open(path_cache, config.cache_size) {
+        Ok(cache) => Some(cache),
+        Err(err) => {
+            println!("ERR: Could not open cache {}", path_cache);
+            println!("{}", err);
+            None
+        }
     }
 }
 
 fn get_downloader_args(url: &str, audio_fmt: &str, path_out: &str) -> Vec<String> {
-    vec![
+    let mut args = vec![
         String::from("-x"),
         String::from(url),
-        String::from("-f"),
-        String::from(audio_fmt),
+        String::from("--audio-format"),
+        String::from(get_downloader_format(audio_fmt)),
         String::from("--output"),
         String::from(path_out),
-    ]
-}
-
-pub fn get_cover(config: &Config, path_cover: &str, album: &Album) -> Vec<u8> {
-    let should_download_cover = !std::path::Path::new(path_cover).exists() || config.force;
-    if should_download_cover {
-        let cover_url = &album.cover;
-        if config.debug {
-            println!("downloading cover")
-        }
-        let resp = reqwest::blocking::get(cover_url);
-
-        match resp {
-            Ok(response) => {
-                let bytes = response.bytes().unwrap().to_vec();
-                if config.download_covers {
-                    write_cover(&path_cover, &bytes).unwrap()
-                }
-                bytes
-            }
-            Err(_) => {
-                panic!("could not download cover")
-            }
-        }
-    } else {
-        if config.debug {
-            println!("Skipping Cover: {}", path_cover)
-        }
-        vec![]
+    ];
+    // Albums imported from a local folder point to the files
+    if url.starts_with("file://") {
+        args.push(String::from("--enable-file-urls"));
     }
+    args
 }
 
-fn write_cover(path: &str, image_bytes: &Vec<u8>) -> Result<(), Error> {
-    let mut file = File::create(path)?;
-    file.write_all(image_bytes)?;
-    Ok(())
+/// Returns the name yt-dlp uses for an audio format.
+fn get_downloader_format(audio_fmt: &str) -> &str {
+    match audio_fmt {
+        "ogg" => "vorbis",
+        fmt => fmt,
+    }
 }
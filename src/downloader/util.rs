@@ -1,8 +1,8 @@
-use std::io::{Error, ErrorKind};
+use std::io::Error;
 
-use crate::{duration_seconds_format, Album, Track};
+use sha2::{Digest, Sha256};
 
-const DELIMITER_DURATION: &str = ":";
+use crate::{duration_seconds_format, duration_seconds_parse, Album, Location, Track};
 
 pub fn has_full(path_full: &str, files: &mut Vec<String>) -> bool {
     if files.contains(&path_full.to_string()) {
@@ -15,82 +15,150 @@ pub fn has_full(path_full: &str, files: &mut Vec<String>) -> bool {
     false
 }
 
-pub fn get_end_time(album: &Album, track: &Track, track_pos_str: &str) -> Result<String, Error> {
-    if let Some(duration) = &track.duration {
-        // FIXME: this math is wrong
-        let start_formatted = get_track_start_time(track)?;
-        let start = duration_seconds_parse(&start_formatted)?;
-        let duration = duration_seconds_parse(duration)?;
-        let end = start + duration;
-        return duration_seconds_format(end);
-    }
-    get_next_track_time(album, track_pos_str)
-}
+/// Directory in an album directory that full files are downloaded into.
+pub const DIR_FULL: &str = ".full";
 
-/// Returns an integer plus one as a string.
-///
-/// # Examples
-///
-/// Basic usage
+/// Returns the path a full file is downloaded to, named by a hash of its URL.
 ///
-/// ```
-/// let two = yar::get_next_str("1");
-/// assert!(!two.is_err());
-/// assert_eq!(two.unwrap(), String::from("2"))
-/// ```
-fn get_next_str(pos: &str) -> Result<String, Error> {
-    let pos_int: i8 = pos.parse().unwrap();
-    let pos_int_next = pos_int + 1;
-    Ok(pos_int_next.to_string())
+/// Albums can be split from more than one upload, eg. a side A and a side B video.
+pub fn get_path_full(out_dir: &str, url: &str, audio_fmt: &str) -> String {
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    format!("{}{}/{}.{}", out_dir, DIR_FULL, &hash[..16], audio_fmt)
 }
 
-/// Converts a duration string to number of seconds.
-
-/// # Example
-///
-/// Basic usage
+/// Returns where a track split from a full file ends.
 ///
-/// ```
-/// let seconds = yar::duration_seconds_parse("4:20");
-///
-/// assert!(!seconds.is_err());
-/// assert_eq!(seconds.unwrap(), 260);
-/// ```
-fn duration_seconds_parse(duration: &str) -> Result<i32, Error> {
-    let mut parts = Vec::from_iter(duration.split(DELIMITER_DURATION));
-    parts.reverse();
-    let mut result = 0;
-    let mut idx = 0;
-    for part in parts {
-        let part_parsed: i32 = part.parse().unwrap();
-        let base: i32 = 60;
-        let to_add = part_parsed * base.pow(idx);
-        result += to_add;
-        idx += 1;
+/// 1. The start of the next track in the same full file, whichever track that is
+/// 2. The start of the track plus its duration
+/// 3. `None`, the track runs to the end of the full file
+pub fn get_end_time(album: &Album, track: &Track, location: &Location) -> Result<Option<String>, Error> {
+    let start = match &location.at {
+        Some(at) => duration_seconds_parse(at)?,
+        None => return Ok(None),
+    };
+    if let Some(next) = get_next_start(album, &location.url, start)? {
+        return Ok(Some(duration_seconds_format(next)?));
     }
-    Ok(result)
+    if let Some(duration) = &track.duration {
+        let end = start + duration_seconds_parse(duration)?;
+        return Ok(Some(duration_seconds_format(end)?));
+    }
+    Ok(None)
 }
 
-fn get_next_track_time(album: &Album, track_pos_str: &str) -> Result<String, Error> {
-    let next_pos = get_next_str(track_pos_str)?;
-    let track = album.tracks.get(&next_pos);
-    match track {
-        Some(track) => get_track_start_time(track),
-        None => Err(Error::new(
-            ErrorKind::NotFound,
-            format!("No track with pos: {}", next_pos),
-        )),
+/// Finds the earliest start after `start` of any track split from the same URL.
+fn get_next_start(album: &Album, url: &str, start: i32) -> Result<Option<i32>, Error> {
+    let mut next: Option<i32> = None;
+    let locations = album.tracks.values().flat_map(|track| track.location.iter());
+    for location in locations.filter(|location| location.url == url) {
+        if let Some(at) = &location.at {
+            let at = duration_seconds_parse(at)?;
+            if at > start && next.is_none_or(|next| at < next) {
+                next = Some(at);
+            }
+        }
     }
+    Ok(next)
 }
 
-fn get_track_start_time(track: &Track) -> Result<String, Error> {
-    for location in &track.location {
-        if let Some(at) = &location.at {
-            return Ok(at.to_string());
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{get_end_time, get_path_full};
+    use crate::{Album, Genres, Location, Track};
+
+    const URL_A: &str = "https://youtube.com/watch?v=a";
+    const URL_B: &str = "https://youtube.com/watch?v=b";
+
+    fn make_track(locations: &[(&str, &str)], duration: Option<&str>) -> Track {
+        Track {
+            name: String::from("Track"),
+            duration: duration.map(String::from),
+            artists: None,
+            artist: None,
+            location: locations
+                .iter()
+                .map(|(url, at)| Location { url: String::from(*url), at: Some(String::from(*at)) })
+                .collect(),
+            sample: None,
+            lyrics: None,
+            wave: None,
+            genre: None,
         }
     }
-    Err(Error::new(
-        ErrorKind::InvalidData,
-        "Next track has no timestamp",
-    ))
+
+    fn make_album(tracks: Vec<(&str, Track)>) -> Album {
+        Album {
+            name: String::from("Album"),
+            artist: String::from("Artist"),
+            genre: Genres::default(),
+            released: chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+            cover: String::new(),
+            track_count: tracks.len() as i8,
+            tracks: HashMap::from_iter(tracks.into_iter().map(|(position, track)| (String::from(position), track))),
+        }
+    }
+
+    fn get_end(album: &Album, position: &str, location: usize) -> Option<String> {
+        let track = &album.tracks[position];
+        get_end_time(album, track, &track.location[location]).unwrap()
+    }
+
+    #[test]
+    fn full_file_path_is_per_url() {
+        let path_a = get_path_full("./album/", URL_A, "mp3");
+        assert!(path_a.starts_with("./album/.full/") && path_a.ends_with(".mp3"));
+        assert_eq!(path_a, get_path_full("./album/", URL_A, "mp3"));
+        assert_ne!(path_a, get_path_full("./album/", URL_B, "mp3"));
+    }
+
+    #[test]
+    fn end_is_next_start_in_same_url() {
+        let album = make_album(vec![
+            ("1", make_track(&[(URL_A, "0:00")], Some("1:00"))),
+            ("2", make_track(&[(URL_A, "2:30")], None)),
+            ("3", make_track(&[(URL_A, "5:00")], None)),
+        ]);
+        assert_eq!(get_end(&album, "1", 0).as_deref(), Some("0:2:30"));
+        assert_eq!(get_end(&album, "2", 0).as_deref(), Some("0:5:0"));
+    }
+
+    #[test]
+    fn end_skips_tracks_of_other_urls() {
+        // Track 2 only exists in the second upload, so it doesn't end track 1
+        let album = make_album(vec![
+            ("1", make_track(&[(URL_A, "0:00"), (URL_B, "0:10")], None)),
+            ("2", make_track(&[(URL_B, "3:00")], None)),
+            ("3", make_track(&[(URL_A, "4:00"), (URL_B, "6:10")], None)),
+        ]);
+        assert_eq!(get_end(&album, "1", 0).as_deref(), Some("0:4:0"));
+        assert_eq!(get_end(&album, "1", 1).as_deref(), Some("0:3:0"));
+    }
+
+    #[test]
+    fn end_falls_back_to_duration() {
+        let album = make_album(vec![
+            ("1", make_track(&[(URL_A, "0:00")], None)),
+            ("2", make_track(&[(URL_A, "2:30")], Some("3:15"))),
+        ]);
+        assert_eq!(get_end(&album, "2", 0).as_deref(), Some("0:5:45"));
+    }
+
+    #[test]
+    fn end_falls_back_to_end_of_full_file() {
+        let album = make_album(vec![
+            ("1", make_track(&[(URL_A, "0:00")], None)),
+            ("2", make_track(&[(URL_A, "2:30")], None)),
+        ]);
+        assert_eq!(get_end(&album, "2", 0), None);
+    }
+
+    #[test]
+    fn end_of_track_without_offset_is_none() {
+        let mut track = make_track(&[], Some("3:00"));
+        track.location.push(Location { url: String::from(URL_A), at: None });
+        let album = make_album(vec![("1", track)]);
+        assert_eq!(get_end(&album, "1", 0), None);
+    }
 }
@@ -0,0 +1,231 @@
+// Acoustic fingerprints, computed locally, to tell if two files are the same recording.
+//
+// Each frame of audio is reduced to 32 bits, one for each pair of neighbouring frequency
+// bands: whether the difference between their energies grew since the previous frame.
+// This survives re-encoding and volume changes, see Haitsma and Kalker, "A Highly Robust
+// Audio Fingerprinting System".
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::lockfile::{prune_lockfile, Lockfile};
+use crate::probe::decode_samples;
+use crate::{Config, Library, Location};
+
+const SAMPLE_RATE: u32 = 11025;
+const FRAME_SIZE: usize = 4096;
+const FRAME_HOP: usize = 2048;
+const BAND_COUNT: usize = 33;
+const BAND_MIN: f64 = 300.0;
+const BAND_MAX: f64 = 2000.0;
+
+/// How many frames two fingerprints can be shifted by when comparing, about 3 seconds.
+///
+/// Searching every shift would compare each frame of one track with each frame of the other,
+/// too slow for every pair of a library, so tracks that start further apart don't match.
+const MAX_OFFSET: isize = 16;
+/// Fewest frames that have to overlap to compare two fingerprints, the whole fingerprint
+/// of a track shorter than this.
+const MIN_OVERLAP: usize = 16;
+
+/// Similarity above which two fingerprints are the same recording.
+pub const SIMILARITY_SAME: f64 = 0.65;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fingerprint(pub Vec<u32>);
+
+impl Fingerprint {
+    /// Encodes the fingerprint as base64, for storing it in text files.
+    pub fn encode(&self) -> String {
+        let bytes: Vec<u8> = self.0.iter().flat_map(|frame| frame.to_be_bytes()).collect();
+        base64::encode(bytes)
+    }
+
+    /// Decodes a fingerprint encoded with [`Fingerprint::encode`].
+    ///
+    /// # Example
+    /// ```
+    /// use yar::fingerprint::Fingerprint;
+    /// let fingerprint = Fingerprint(vec![1, u32::MAX]);
+    /// assert_eq!(Fingerprint::decode(&fingerprint.encode()).unwrap(), fingerprint);
+    /// ```
+    pub fn decode(encoded: &str) -> Result<Fingerprint, Error> {
+        let bytes = base64::decode(encoded).map_err(|err| Error::new(ErrorKind::InvalidData, err.to_string()))?;
+        if bytes.len() % 4 != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Fingerprint has a partial frame"));
+        }
+        let frames = bytes
+            .chunks_exact(4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        Ok(Fingerprint(frames))
+    }
+
+    /// Returns how similar two fingerprints are, from 0 to 1.
+    ///
+    /// Fingerprints are shifted against each other by up to about 3 seconds, so tracks that
+    /// were split at slightly different times still match. Tracks that start further apart,
+    /// eg. one with a longer intro, are not the same recording to this.
+    ///
+    /// # Example
+    /// ```
+    /// use yar::fingerprint::Fingerprint;
+    /// let a = Fingerprint((0..64).map(|frame: u32| frame.wrapping_mul(2654435761)).collect());
+    /// let b = Fingerprint(a.0[3..].to_vec());
+    /// assert_eq!(a.similarity(&b), 1.0);
+    /// assert!(a.similarity(&Fingerprint(vec![0; 64])) < 0.65);
+    /// ```
+    pub fn similarity(&self, other: &Fingerprint) -> f64 {
+        let min_overlap = MIN_OVERLAP.min(self.0.len()).min(other.0.len()).max(1);
+        let mut best: f64 = 0.0;
+        for offset in -MAX_OFFSET..=MAX_OFFSET {
+            let (a, b) = match offset >= 0 {
+                true => (self.0.get(offset as usize..), Some(other.0.as_slice())),
+                false => (Some(self.0.as_slice()), other.0.get(offset.unsigned_abs()..)),
+            };
+            let (a, b) = match (a, b) {
+                (Some(a), Some(b)) => (a, b),
+                _ => continue,
+            };
+            let overlap = a.len().min(b.len());
+            if overlap < min_overlap {
+                continue;
+            }
+            let errors: u32 = a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum();
+            best = best.max(1.0 - errors as f64 / (overlap * 32) as f64);
+        }
+        best
+    }
+}
+
+/// Computes the fingerprint of an audio file.
+pub fn get_fingerprint(path: &str) -> Result<Fingerprint, Error> {
+    let samples = decode_samples(path, SAMPLE_RATE)?;
+    if samples.len() < FRAME_SIZE * 2 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} is too short to fingerprint", path)));
+    }
+    Ok(make_fingerprint(&samples))
+}
+
+/// Computes the fingerprint of mono samples at 11025 Hz.
+pub fn make_fingerprint(samples: &[i16]) -> Fingerprint {
+    let fft = FftPlanner::new().plan_fft_forward(FRAME_SIZE);
+    let window: Vec<f64> = (0..FRAME_SIZE)
+        .map(|idx| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * idx as f64 / FRAME_SIZE as f64).cos())
+        .collect();
+    let bands = get_band_bins();
+
+    let mut frames = vec![];
+    let mut previous: Option<Vec<f64>> = None;
+    let mut start = 0;
+    while start + FRAME_SIZE <= samples.len() {
+        let energies = get_band_energies(&fft, &window, &bands, &samples[start..start + FRAME_SIZE]);
+        if let Some(previous) = &previous {
+            let mut frame = 0u32;
+            for band in 0..BAND_COUNT - 1 {
+                let difference = (energies[band] - energies[band + 1]) - (previous[band] - previous[band + 1]);
+                if difference > 0.0 {
+                    frame |= 1 << band;
+                }
+            }
+            frames.push(frame);
+        }
+        previous = Some(energies);
+        start += FRAME_HOP;
+    }
+    Fingerprint(frames)
+}
+
+/// Returns the range of FFT bins of each band, spaced logarithmically like pitch.
+fn get_band_bins() -> Vec<(usize, usize)> {
+    let bin_width = SAMPLE_RATE as f64 / FRAME_SIZE as f64;
+    let get_bin = |band: usize| {
+        let frequency = BAND_MIN * (BAND_MAX / BAND_MIN).powf(band as f64 / BAND_COUNT as f64);
+        (frequency / bin_width).round() as usize
+    };
+    (0..BAND_COUNT)
+        .map(|band| (get_bin(band), get_bin(band + 1).max(get_bin(band) + 1)))
+        .collect()
+}
+
+fn get_band_energies(fft: &Arc<dyn Fft<f64>>, window: &[f64], bands: &[(usize, usize)], samples: &[i16]) -> Vec<f64> {
+    let mut buffer: Vec<Complex<f64>> = samples
+        .iter()
+        .zip(window)
+        .map(|(sample, window)| Complex::new(*sample as f64 * window, 0.0))
+        .collect();
+    fft.process(&mut buffer);
+    bands
+        .iter()
+        .map(|(start, end)| buffer[*start..*end].iter().map(|bin| bin.norm_sqr()).sum())
+        .collect()
+}
+
+/// Stores the fingerprint of a track in the lockfile, warning when a downloaded track
+/// sounds different than it did on the last sync.
+///
+/// Tracks that weren't downloaded, `source` is `None`, are only fingerprinted once.
+pub fn update_fingerprint(config: &Config, lockfile: &mut Lockfile, track_id: &str, path: &str, source: Option<&Location>) {
+    let locked = lockfile.tracks.entry(track_id.to_owned()).or_default();
+    if source.is_none() && locked.fingerprint.is_some() {
+        return;
+    }
+    if config.debug {
+        println!("fingerprinting: {}", path);
+    }
+    let fingerprint = match get_fingerprint(path) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+            println!("ERR: Could not fingerprint {}", path);
+            println!("{}", err);
+            return;
+        }
+    };
+
+    let previous = locked.fingerprint.as_ref().and_then(|previous| Fingerprint::decode(previous).ok());
+    if let Some(previous) = previous {
+        let similarity = previous.similarity(&fingerprint);
+        if similarity < SIMILARITY_SAME {
+            println!(
+                "WARN: {} sounds different than on the last sync ({:.0}% similar)",
+                path,
+                similarity * 100.0
+            );
+            if let (Some(old), Some(new)) = (&locked.source, source) {
+                println!("last downloaded from {}, now from {}", old, new.url);
+            }
+        }
+    }
+    locked.fingerprint = Some(fingerprint.encode());
+    if let Some(source) = source {
+        locked.source = Some(source.url.to_owned());
+    }
+}
+
+/// Finds pairs of tracks in a lockfile that are the same recording, with their similarity.
+///
+/// Tracks that are no longer in the library are removed from the lockfile first.
+pub fn find_duplicates(lockfile: &mut Lockfile, library: &Library) -> Vec<(String, String, f64)> {
+    prune_lockfile(lockfile, library);
+    let fingerprints: Vec<(&String, Fingerprint)> = lockfile
+        .tracks
+        .iter()
+        .filter_map(|(track_id, locked)| {
+            let fingerprint = Fingerprint::decode(locked.fingerprint.as_ref()?).ok()?;
+            Some((track_id, fingerprint))
+        })
+        .collect();
+
+    let mut duplicates = vec![];
+    for (idx, (track_id, fingerprint)) in fingerprints.iter().enumerate() {
+        for (other_id, other) in &fingerprints[idx + 1..] {
+            let similarity = fingerprint.similarity(other);
+            if similarity >= SIMILARITY_SAME {
+                duplicates.push((track_id.to_string(), other_id.to_string(), similarity));
+            }
+        }
+    }
+    duplicates
+}
@@ -0,0 +1,311 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use url::Url;
+
+use super::{get_path_album_file, set_default_genre, write_album, Fetcher};
+use crate::{Album, Genres, Location, Track, TrackArtist};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampAlbum {
+    pub name: String,
+    pub by_artist: BandcampArtist,
+    pub num_tracks: i8,
+    pub date_published: String,
+    pub image: String,
+    pub keywords: Option<serde_json::Value>,
+    pub track: BandcampAlbumTracks,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampRecording {
+    pub name: String,
+    pub by_artist: BandcampArtist,
+    pub date_published: String,
+    pub image: String,
+    pub duration: String,
+    pub keywords: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BandcampClientItem {
+    page_url: String,
+}
+
+pub type BandcampAlbumTracks = BandcampList<BandcampTrack>;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampList<T> {
+    pub item_list_element: Vec<BandcampListItem<T>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampListItem<T> {
+    pub position: i8,
+    pub item: T,
+}
+
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampArtist {
+    pub name: String
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BandcampTrack {
+    pub name: String,
+    pub duration: String,
+    pub by_artist: Option<BandcampArtist>,
+    pub main_entity_of_page: String,
+}
+
+pub fn get_bandcamp_album(json: &str) -> Result<BandcampAlbum, Error> {
+    let bandcamp_album: BandcampAlbum = serde_json::from_str(json)?;
+    Ok(bandcamp_album)
+}
+
+fn parse_released(date_published: &str) -> NaiveDate {
+    let fmt = "%d %b %Y %T %Z";
+    NaiveDate::parse_from_str(date_published, fmt).unwrap()
+}
+
+pub fn parse(album: BandcampAlbum) -> Result<Album, Error> {
+    let name = album.name;
+    let artist = album.by_artist.name;
+    let released = parse_released(&album.date_published);
+    let genre = parse_genres(&album.keywords);
+    let cover = album.image;
+    let track_count = album.num_tracks;
+    let tracks = parse_tracks(album.track);
+
+    let parsed = Album { name, artist, genre, released, cover, tracks, track_count, };
+    Ok(parsed)
+}
+
+fn parse_tracks(tracks: BandcampAlbumTracks) -> HashMap<String, Track> {
+    let mut result = HashMap::new();
+    for element in tracks.item_list_element {
+        let position = element.position.to_string();
+        let track = parse_track(&element.item);
+        result.insert(position, track);
+    }
+    result
+}
+
+fn parse_track(track: &BandcampTrack) -> Track {
+    let name = track.name.to_owned();
+    let duration = parse_duration(&track.duration);
+    let artists = parse_artists(track);
+    let location = parse_location(track);
+    let sample = None;
+    let lyrics = None;
+    let wave = None;
+
+    Track { name, duration, artists, location, sample, lyrics, wave, artist: None, genre: None }
+}
+
+/// Converts the tags of a release to genres, they are either a list or a comma separated string.
+pub fn parse_genres(keywords: &Option<serde_json::Value>) -> Genres {
+    match keywords {
+        Some(serde_json::Value::Array(keywords)) => {
+            let mut genres: Vec<String> = vec![];
+            for keyword in keywords.iter().filter_map(|keyword| keyword.as_str()) {
+                if !genres.iter().any(|genre| genre.eq_ignore_ascii_case(keyword)) {
+                    genres.push(String::from(keyword));
+                }
+            }
+            Genres(genres)
+        }
+        Some(serde_json::Value::String(keywords)) => Genres::parse(keywords),
+        _ => Genres::default(),
+    }
+}
+
+pub fn parse_duration(duration: &str) -> Option<String> {
+    let result = String::from(duration)
+        .replace("P", "")
+        .replace("S", "")
+        .replace("H", ":")
+        .replace("M", ":");
+
+    Some(result)
+}
+
+pub fn parse_artists(track: &BandcampTrack) -> Option<Vec<TrackArtist>> {
+    match &track.by_artist {
+        Some(artists) => {
+            let names_str = artists.name.to_owned();
+            let names = names_str
+                .split(", ")
+                .flat_map(|name| -> Vec<String> {
+                    String::from(name)
+                        .split(" & ")
+                        .map(String::from)
+                        .collect()
+                })
+                .map(|id| TrackArtist { id, r#for: None })
+                .collect();
+            Some(names)
+        },
+        None => None,
+    }
+
+}
+
+fn parse_location(track: &BandcampTrack) -> Vec<Location> {
+    let url = track.main_entity_of_page.to_owned();
+    let at = None;
+    let location = Location { url, at };
+
+    vec![location]
+}
+
+/// Converts a bandcamp single track release into a one track album.
+pub fn parse_recording(url: &str, recording: BandcampRecording) -> Result<Album, Error> {
+    let name = recording.name;
+    let artist = recording.by_artist.name;
+    let released = parse_released(&recording.date_published);
+    let genre = parse_genres(&recording.keywords);
+    let cover = recording.image;
+    let track_count = 1;
+    let mut tracks = HashMap::new();
+
+    let track = Track {
+        name: name.clone(),
+        duration: parse_duration(&recording.duration),
+        artists: None,
+        artist: None,
+        location: vec![Location { url: String::from(url), at: None }],
+        sample: None,
+        lyrics: None,
+        wave: None,
+        genre: None,
+    };
+    tracks.insert(String::from("1"), track);
+
+    Ok(Album { name, artist, genre, released, cover, tracks, track_count })
+}
+
+pub fn get_album_from_url(fetcher: &dyn Fetcher, url: &str) -> Result<Album, Error> {
+    let html = fetcher.fetch(url)?;
+    let json = extract_json_from_html(&html)?;
+    let value: serde_json::Value = serde_json::from_str(&json)?;
+    if value["@type"] == "MusicRecording" {
+        let recording: BandcampRecording = serde_json::from_value(value)?;
+        return parse_recording(url, recording);
+    }
+    let bandcamp = get_bandcamp_album(&json)?;
+    let parsed = parse(bandcamp)?;
+    Ok(parsed)
+}
+
+/// Returns the scheme and host of a URL, eg. `https://artist.bandcamp.com`.
+fn get_url_origin(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+    let host = rest.split('/').next().unwrap_or(rest);
+    format!("{}://{}", scheme, host)
+}
+
+/// Checks if a bandcamp URL is an artist or label root page rather than a release.
+///
+/// URLs that can't be parsed, eg. without a scheme, are not.
+pub fn is_discography_url(url: &str) -> bool {
+    match Url::parse(url) {
+        Ok(url) => matches!(url.path().trim_end_matches('/'), "" | "/music"),
+        Err(_) => false,
+    }
+}
+
+/// Makes a release link found on a discography page absolute and strips its query.
+fn parse_release_url(origin: &str, href: &str) -> Option<String> {
+    let href = href.split(['?', '#']).next().unwrap_or(href);
+    if !href.contains("/album/") && !href.contains("/track/") {
+        return None;
+    }
+    if href.starts_with('/') {
+        Some(format!("{}{}", origin, href))
+    } else if href.starts_with("http") {
+        Some(href.to_owned())
+    } else {
+        None
+    }
+}
+
+/// Finds every album and track release linked from a discography page.
+pub fn extract_release_urls(origin: &str, html: &str) -> Vec<String> {
+    let grid = match html.split_once("id=\"music-grid\"") {
+        Some((_, after)) => after.split("</ol>").next().unwrap_or(after),
+        None => html,
+    };
+
+    let mut hrefs: Vec<String> = grid
+        .split("href=\"")
+        .skip(1)
+        .filter_map(|part| part.split('"').next())
+        .map(String::from)
+        .collect();
+
+    // Releases past the first page of the grid are only listed in this attribute
+    if let Some((_, after)) = grid.split_once("data-client-items=\"") {
+        let items_html = after.split('"').next().unwrap_or("");
+        let items_json = items_html.replace("&quot;", "\"").replace("&amp;", "&");
+        if let Ok(items) = serde_json::from_str::<Vec<BandcampClientItem>>(&items_json) {
+            hrefs.extend(items.into_iter().map(|item| item.page_url));
+        }
+    }
+
+    let mut urls: Vec<String> = vec![];
+    for url in hrefs.iter().filter_map(|href| parse_release_url(origin, href)) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Writes an album file for every release on a bandcamp artist or label page.
+pub fn import_discography(fetcher: &dyn Fetcher, url: &str, path_out: &str, genre_default: &Genres) -> Result<(), Error> {
+    let origin = get_url_origin(url);
+    let url_music = format!("{}/music", origin);
+    let html = fetcher.fetch(&url_music)?;
+    let urls = extract_release_urls(&origin, &html);
+    if urls.is_empty() {
+        return Err(Error::new(ErrorKind::NotFound, "No releases found on page"));
+    }
+
+    for url_release in urls {
+        let mut album = match get_album_from_url(fetcher, &url_release) {
+            Ok(album) => album,
+            Err(err) => {
+                eprintln!("ERR: Could not import {}", url_release);
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+        set_default_genre(&mut album, genre_default);
+        let path_album = get_path_album_file(path_out, &album);
+        if Path::new(&path_album).exists() {
+            eprintln!("Skipping: {}", path_album);
+            continue;
+        }
+        write_album(&path_album, &album)?;
+        eprintln!("Wrote: {}", path_album);
+    }
+    Ok(())
+}
+
+pub fn extract_json_from_html(html: &str) -> Result<String, Error> {
+    let after = html.split("<script type=\"application/ld+json\">").last().unwrap().to_owned();
+    let parts: Vec<&str> = after.split("</script>").collect();
+    let json = parts[0].trim().to_owned();
+    Ok(json)
+}
@@ -0,0 +1,190 @@
+// Converts a folder of tagged music files to Album definitions
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{canonicalize, create_dir_all, read_dir, write};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+
+use super::{get_path_album_file, set_default_genre, write_album};
+use crate::cover::get_cover_extension;
+use crate::tagger::{read_tags, Tags};
+use crate::{Album, Genres, Location, Sample, Track, TrackArtist, AUDIO_FORMATS};
+
+/// Imports every album in a folder into `<dir>/artists/<artist>/<album>.yml`.
+///
+/// Files are grouped by their album and album artist tags. Embedded covers are saved
+/// next to the album file. Album files that already exist are skipped.
+pub fn import_folder(path_in: &str, path_out: &str, genre_default: &Genres) -> Result<(), Error> {
+    let mut albums: BTreeMap<(String, String), Vec<(PathBuf, Tags)>> = BTreeMap::new();
+    for path in find_audio_files(Path::new(path_in))? {
+        let path_str = path.to_string_lossy().to_string();
+        let tags = match read_tags(&path_str) {
+            Ok(tags) => tags,
+            Err(err) => {
+                eprintln!("ERR: Could not read tags of {}", path_str);
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+        if tags.album.is_empty() {
+            eprintln!("Skipping file without album: {}", path_str);
+            continue;
+        }
+        let artist = match tags.album_artist.is_empty() {
+            true => tags.artist.to_owned(),
+            false => tags.album_artist.to_owned(),
+        };
+        albums.entry((artist, tags.album.to_owned())).or_default().push((path, tags));
+    }
+
+    for ((artist, name), tracks) in albums {
+        let (mut album, cover) = make_album(&artist, &name, &tracks);
+        set_default_genre(&mut album, genre_default);
+        let path_album = get_path_album_file(path_out, &album);
+        if Path::new(&path_album).exists() {
+            eprintln!("Skipping: {}", path_album);
+            continue;
+        }
+        if !cover.is_empty() {
+            let path_cover = Path::new(&path_album).with_extension(get_cover_extension(&cover));
+            create_dir_all(path_cover.parent().unwrap())?;
+            write(&path_cover, &cover)?;
+            album.cover = get_file_url(&path_cover);
+        }
+        write_album(&path_album, &album)?;
+        eprintln!("Wrote: {}", path_album);
+    }
+    Ok(())
+}
+
+/// Finds the audio files in a folder and its subfolders, sorted by path.
+fn find_audio_files(path: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = vec![];
+    for entry in read_dir(path)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(find_audio_files(&path)?);
+            continue;
+        }
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        if AUDIO_FORMATS.contains(&extension.as_str()) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Creates an album from the tags of its files, returns the album and its embedded cover.
+fn make_album(artist: &str, name: &str, files: &[(PathBuf, Tags)]) -> (Album, Vec<u8>) {
+    let genre = Genres(get_most_common(files.iter().map(|(_, tags)| &tags.genres)).cloned().unwrap_or_default());
+    let year = get_most_common(files.iter().map(|(_, tags)| tags.year)).unwrap_or_default();
+    let cover = files
+        .iter()
+        .map(|(_, tags)| &tags.cover)
+        .find(|cover| !cover.is_empty())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut tracks: HashMap<String, Track> = HashMap::new();
+    for (idx, (path, tags)) in files.iter().enumerate() {
+        // Files without a track number or with a duplicate one are added in order
+        let mut position = match tags.track {
+            0 => idx + 1,
+            track => track as usize,
+        };
+        while tracks.contains_key(&position.to_string()) {
+            position += 1;
+        }
+        let track_genre = Genres(tags.genres.clone());
+        tracks.insert(position.to_string(), Track {
+            name: get_name(path, tags),
+            duration: None,
+            artists: parse_artists(artist, &tags.artist),
+            artist: None,
+            location: vec![Location { url: get_file_url(path), at: None }],
+            sample: parse_samples(&tags.comment),
+            lyrics: tags.lyrics.clone(),
+            wave: None,
+            genre: Some(track_genre).filter(|track_genre| !track_genre.is_empty() && track_genre != &genre),
+        });
+    }
+
+    let track_total = files.iter().map(|(_, tags)| tags.track_total as usize).max().unwrap_or_default();
+    let album = Album {
+        name: name.to_owned(),
+        artist: artist.to_owned(),
+        genre,
+        released: NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default(),
+        cover: String::new(),
+        track_count: track_total.max(tracks.len()).try_into().unwrap_or(i8::MAX),
+        tracks,
+    };
+    (album, cover)
+}
+
+/// Returns the value most files have, the first one on a tie.
+fn get_most_common<T: PartialEq + Clone>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts: Vec<(T, usize)> = vec![];
+    for value in values {
+        match counts.iter_mut().find(|(existing, _)| existing == &value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    let max = counts.iter().map(|(_, count)| *count).max()?;
+    counts.into_iter().find(|(_, count)| *count == max).map(|(value, _)| value)
+}
+
+fn get_name(path: &Path, tags: &Tags) -> String {
+    if !tags.title.is_empty() {
+        return tags.title.to_owned();
+    }
+    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Returns the track artists when they aren't the album artist.
+fn parse_artists(album_artist: &str, artist: &str) -> Option<Vec<TrackArtist>> {
+    if artist.is_empty() || artist == album_artist {
+        return None;
+    }
+    let artists = artist
+        .split(", ")
+        .map(|id| TrackArtist { id: id.to_owned(), r#for: None })
+        .collect();
+    Some(artists)
+}
+
+/// Parses the samples from a comment in the format yar tags them.
+///
+/// # Example
+/// ```
+/// let samples = yar::importer::folder::parse_samples("SAMPLE: DJ Screw, Sippin Codeine\nripped by me").unwrap();
+/// assert_eq!(samples.len(), 1);
+/// assert_eq!(samples[0].artist, "DJ Screw");
+/// assert_eq!(samples[0].name, "Sippin Codeine");
+/// ```
+pub fn parse_samples(comment: &str) -> Option<Vec<Sample>> {
+    let samples: Vec<Sample> = comment
+        .lines()
+        .filter_map(|line| line.strip_prefix("SAMPLE: "))
+        .filter_map(|sample| sample.split_once(", "))
+        .map(|(artist, name)| Sample {
+            artist: artist.to_owned(),
+            name: name.to_owned(),
+            r#type: String::from("sample"),
+        })
+        .collect();
+    Some(samples).filter(|samples| !samples.is_empty())
+}
+
+/// Returns a `file://` URL of a local file.
+fn get_file_url(path: &Path) -> String {
+    let path = canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    format!("file://{}", path.to_string_lossy())
+}
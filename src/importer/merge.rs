@@ -0,0 +1,104 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{get_position_key, is_same_position, Album, Track};
+
+/// A single field that changed while merging a scraped album into an existing one.
+#[derive(Debug, PartialEq)]
+pub enum Change {
+    Cover(String, String),
+    TrackCount(i8, i8),
+    TrackAdded(String, String),
+    TrackName(String, String, String),
+    TrackDuration(String, Option<String>, Option<String>),
+    TrackLocation(String, String),
+    TrackMissing(String, String),
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+        let none = String::from("-");
+        match self {
+            Change::Cover(old, new) => write!(f, "~ cover: {} -> {}", old, new),
+            Change::TrackCount(old, new) => write!(f, "~ track_count: {} -> {}", old, new),
+            Change::TrackAdded(pos, name) => write!(f, "+ {}: {}", pos, name),
+            Change::TrackName(pos, old, new) => write!(f, "~ {} name: {} -> {}", pos, old, new),
+            Change::TrackDuration(pos, old, new) => write!(
+                f,
+                "~ {} duration: {} -> {}",
+                pos,
+                old.as_ref().unwrap_or(&none),
+                new.as_ref().unwrap_or(&none)
+            ),
+            Change::TrackLocation(pos, url) => write!(f, "+ {} location: {}", pos, url),
+            Change::TrackMissing(pos, name) => write!(f, "! {}: {} is not on the source page", pos, name),
+        }
+    }
+}
+
+/// Updates an existing album with freshly scraped data.
+///
+/// Only the fields a scraper knows about are replaced (cover, track count, track names and
+/// durations). Curated data such as samples, lyrics, artist roles and extra locations is kept,
+/// new tracks are added as they are and tracks missing from the source are left alone.
+pub fn merge_album(existing: &mut Album, scraped: Album) -> Vec<Change> {
+    let mut changes = vec![];
+
+    if existing.cover != scraped.cover {
+        changes.push(Change::Cover(existing.cover.clone(), scraped.cover.clone()));
+        existing.cover = scraped.cover;
+    }
+    if existing.track_count != scraped.track_count {
+        changes.push(Change::TrackCount(existing.track_count, scraped.track_count));
+        existing.track_count = scraped.track_count;
+    }
+
+    let mut scraped_tracks: Vec<(String, Track)> = scraped.tracks.into_iter().collect();
+    scraped_tracks.sort_by_key(|(pos, _)| get_position_key(pos));
+
+    let mut missing: Vec<(&String, &Track)> = existing.tracks
+        .iter()
+        .filter(|(pos, _)| !scraped_tracks.iter().any(|(pos_scraped, _)| is_same_position(pos_scraped, pos)))
+        .collect();
+    missing.sort_by_key(|(pos, _)| get_position_key(pos));
+    let mut changes_missing: Vec<Change> = missing
+        .into_iter()
+        .map(|(pos, track)| Change::TrackMissing(pos.clone(), track.name.clone()))
+        .collect();
+
+    for (pos, track_scraped) in scraped_tracks {
+        // The existing position is kept, eg. `01` when the source numbers it `1`
+        let existing_track = existing.tracks
+            .iter_mut()
+            .find(|(pos_existing, _)| is_same_position(pos_existing, &pos));
+        match existing_track {
+            Some((pos_existing, track)) => merge_track(&mut changes, pos_existing, track, track_scraped),
+            None => {
+                changes.push(Change::TrackAdded(pos.clone(), track_scraped.name.clone()));
+                existing.tracks.insert(pos, track_scraped);
+            }
+        }
+    }
+
+    changes.append(&mut changes_missing);
+    changes
+}
+
+fn merge_track(changes: &mut Vec<Change>, pos: &str, track: &mut Track, scraped: Track) {
+    if track.name != scraped.name {
+        changes.push(Change::TrackName(pos.to_owned(), track.name.clone(), scraped.name.clone()));
+        track.name = scraped.name;
+    }
+    if scraped.duration.is_some() && track.duration != scraped.duration {
+        changes.push(Change::TrackDuration(pos.to_owned(), track.duration.clone(), scraped.duration.clone()));
+        track.duration = scraped.duration;
+    }
+    for location in scraped.location {
+        if !track.location.iter().any(|existing| existing.url == location.url) {
+            changes.push(Change::TrackLocation(pos.to_owned(), location.url.clone()));
+            track.location.push(location);
+        }
+    }
+    if track.wave.is_none() {
+        track.wave = scraped.wave;
+    }
+}
@@ -0,0 +1,81 @@
+// Converts bandcamp and soundcloud pages to Album definitions
+pub mod bandcamp;
+pub mod folder;
+pub mod merge;
+pub mod soundcloud;
+
+use std::fs::{create_dir_all, File};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::{parse_name, Album, Genres};
+
+/// Loads the contents of a URL, so the scrapers can be run without network access.
+pub trait Fetcher {
+    fn fetch(&self, url: &str) -> Result<String, Error>;
+}
+
+/// Fetches pages over HTTP.
+pub struct HttpFetcher;
+
+impl Fetcher for HttpFetcher {
+    fn fetch(&self, url: &str) -> Result<String, Error> {
+        reqwest::blocking::get(url)
+            .map_err(Error::other)?
+            .text()
+            .map_err(Error::other)
+    }
+}
+
+/// Returns the path of an album file in the layout `yar build` scans.
+///
+/// # Example
+/// ```
+/// let album = &yar::Album {
+///     artist: String::from("My Artist"),
+///     name: String::from("Album Name"),
+///     cover: String::new(),
+///     genre: yar::Genres::default(),
+///     released: chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+///     track_count: 0,
+///     tracks: std::collections::HashMap::new(),
+/// };
+/// let path = yar::importer::get_path_album_file("./db/", album);
+/// assert_eq!(path, "./db/artists/my-artist/album-name.yml");
+/// ```
+pub fn get_path_album_file(path_out: &str, album: &Album) -> String {
+    format!(
+        "{}/artists/{}/{}.yml",
+        path_out.trim_end_matches('/'),
+        parse_name(&album.artist),
+        parse_name(&album.name)
+    )
+}
+
+/// Sets the genre of an album when the source page didn't have any.
+pub fn set_default_genre(album: &mut Album, genre_default: &Genres) {
+    if album.genre.is_empty() {
+        album.genre = genre_default.clone();
+    }
+}
+
+/// Reads an album file.
+pub fn read_album(path: &str) -> Result<Album, Error> {
+    let file = File::open(path)?;
+    match serde_yaml::from_reader(file) {
+        Ok(album) => Ok(album),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("Could not read album {}: {}", path, err)))
+    }
+}
+
+/// Writes an album file, creating its directory if needed.
+pub fn write_album(path: &str, album: &Album) -> Result<(), Error> {
+    if let Some(parent) = Path::new(path).parent() {
+        create_dir_all(parent)?;
+    }
+    let file = File::create(path)?;
+    match serde_yaml::to_writer(file, album) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::new(ErrorKind::InvalidData, "Could not serialize album"))
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind};
+
+use chrono::{DateTime, NaiveDate};
+use serde::Deserialize;
+
+use super::Fetcher;
+use crate::{duration_seconds_format, Album, Genres, Location, Track, TrackArtist, Wave};
+
+const API_URL: &str = "https://api-v2.soundcloud.com";
+
+/// Most track ids the api-v2 takes in one request.
+const API_TRACKS_MAX: usize = 50;
+
+pub type SoundcloudHydration = Vec<SoundcloudHydrationData>;
+
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoundcloudHydrationData {
+    pub hydratable: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SoundcloudSound {
+    pub artwork_url: Option<String>,
+    pub title: String,
+    pub genre: Option<String>,
+    pub waveform_url: String,
+    pub user: SoundcloudUser,
+    pub created_at: String,
+    pub duration: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SoundcloudPlaylist {
+    pub artwork_url: Option<String>,
+    pub title: String,
+    pub genre: Option<String>,
+    pub user: SoundcloudUser,
+    pub created_at: String,
+    pub release_date: Option<String>,
+    pub tracks: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SoundcloudPlaylistTrack {
+    pub permalink_url: String,
+    #[serde(flatten)]
+    pub sound: SoundcloudSound,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SoundcloudUser {
+    pub username: String,
+    pub avatar_url: String,
+}
+
+#[derive(Debug)]
+pub enum SoundcloudResource {
+    Sound(SoundcloudSound),
+    Playlist(SoundcloudPlaylist),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SoundcloudWave {
+    pub width: i32,
+    pub height: i32,
+    pub samples: Vec<i32>,
+}
+
+pub fn soundcloud_extract_json_from_html(html: &str) -> Result<String, Error> {
+    let after = html.split("<script>window.__sc_hydration = ").last().unwrap().to_owned();
+    let parts: Vec<&str> = after.split(";</script>").collect();
+    let json = parts[0].trim().to_owned();
+    Ok(json)
+}
+
+fn soundcloud_get_json(fetcher: &dyn Fetcher, url: &str) -> Result<String, Error> {
+    let html = fetcher.fetch(url)?;
+    soundcloud_extract_json_from_html(&html)
+}
+
+/// Returns the API client id of the page, which the api-v2 needs.
+pub fn soundcloud_parse_client_id(json: &str) -> Option<String> {
+    let hydration: SoundcloudHydration = serde_json::from_str(json).ok()?;
+    hydration
+        .into_iter()
+        .find(|data| data.hydratable == "apiClient")
+        .and_then(|data| data.data["id"].as_str().map(String::from))
+}
+
+pub fn soundcloud_parse_json(json: &str) -> Result<SoundcloudResource, Error> {
+    let hydration: SoundcloudHydration = serde_json::from_str(json)?;
+    for data in hydration {
+        match data.hydratable.as_str() {
+            "sound" => {
+                let sound: SoundcloudSound = serde_json::from_value(data.data)?;
+                return Ok(SoundcloudResource::Sound(sound))
+            }
+            "playlist" => {
+                let playlist: SoundcloudPlaylist = serde_json::from_value(data.data)?;
+                return Ok(SoundcloudResource::Playlist(playlist))
+            }
+            _ => (),
+        }
+    }
+    Err(Error::new(ErrorKind::InvalidData, "Could not parse Soundcloud data"))
+}
+
+/// Convert a soundcloud wave type into a Wave type.
+pub fn soundcloud_parse_wave(wave: SoundcloudWave) -> Wave {
+    let max = wave.height;
+    let length = wave.width;
+    let points = wave.samples
+        .iter()
+        .map(|point| ((*point as f32 / max as f32) * u8::MAX as f32) as u8)
+        .collect();
+    Wave {
+        length,
+        points,
+    }
+}
+
+fn get_wave(fetcher: &dyn Fetcher, url_wave: &str) -> Result<Wave, Error> {
+    let wave_json = fetcher.fetch(url_wave)?;
+    let wave_sc: SoundcloudWave = serde_json::from_str(&wave_json)?;
+    let wave = soundcloud_parse_wave(wave_sc);
+    Ok(wave)
+}
+
+/// Returns the largest version of a soundcloud artwork.
+fn soundcloud_parse_cover(artwork_url: &str) -> String {
+    artwork_url.replace("-large.jpg", "-t500x500.jpg")
+}
+
+fn soundcloud_parse_released(date: &str) -> Result<NaiveDate, Error> {
+    match DateTime::parse_from_rfc3339(date) {
+        Ok(date) => Ok(date.date_naive()),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("Invalid release date {}: {}", date, err))),
+    }
+}
+
+fn soundcloud_parse_track(fetcher: &dyn Fetcher, url: &str, sound: &SoundcloudSound, artists: Option<Vec<TrackArtist>>) -> Result<Track, Error> {
+    let duration = Some(duration_seconds_format(sound.duration / 1000)?);
+    let location = vec![Location {
+        url: String::from(url),
+        at: None,
+    }];
+    let wave = Some(get_wave(fetcher, &sound.waveform_url)?);
+
+    Ok(Track {
+        name: sound.title.clone(),
+        duration,
+        artists,
+        artist: None,
+        location,
+        sample: None,
+        lyrics: None,
+        wave,
+        genre: None,
+    })
+}
+
+pub fn soundcloud_parse_sound(fetcher: &dyn Fetcher, url: &str, sound: SoundcloudSound) -> Result<Album, Error> {
+    let track = soundcloud_parse_track(fetcher, url, &sound, None)?;
+    let name = sound.title;
+    let artist = sound.user.username;
+    let genre = Genres::parse(&sound.genre.unwrap_or_default());
+    let released = soundcloud_parse_released(&sound.created_at)?;
+    let cover = soundcloud_parse_cover(&sound.artwork_url.unwrap_or(sound.user.avatar_url));
+    let track_count = 1;
+    let mut tracks = HashMap::new();
+    tracks.insert(String::from("1"), track);
+
+    let album = Album {
+        name,
+        artist,
+        genre,
+        released,
+        cover,
+        tracks,
+        track_count,
+    };
+
+    Ok(album)
+}
+
+pub fn soundcloud_parse_playlist(fetcher: &dyn Fetcher, playlist: SoundcloudPlaylist, client_id: Option<&str>) -> Result<Album, Error> {
+    let mut tracks = HashMap::new();
+    let mut genres_tracks = vec![];
+    let mut cover_track = None;
+
+    let values = soundcloud_hydrate_tracks(fetcher, playlist.tracks, client_id)?;
+    for (idx, value) in values.into_iter().enumerate() {
+        let position = idx + 1;
+        let playlist_track: SoundcloudPlaylistTrack = match serde_json::from_value(value) {
+            Ok(track) => track,
+            Err(err) => return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Could not parse track {} of the set: {}", position, err),
+            )),
+        };
+        let sound = playlist_track.sound;
+        let artists = Some(vec![TrackArtist { id: sound.user.username.clone(), r#for: None }]);
+        let mut track = soundcloud_parse_track(fetcher, &playlist_track.permalink_url, &sound, artists)?;
+
+        let genre_track = Genres::parse(&sound.genre.unwrap_or_default());
+        for genre in &genre_track.0 {
+            if !genres_tracks.contains(genre) {
+                genres_tracks.push(genre.clone());
+            }
+        }
+        track.genre = Some(genre_track).filter(|genre| !genre.is_empty());
+        cover_track = cover_track.or(sound.artwork_url);
+        tracks.insert(position.to_string(), track);
+    }
+
+    let name = playlist.title;
+    let artist = playlist.user.username;
+    let mut genre = Genres::parse(&playlist.genre.unwrap_or_default());
+    if genre.is_empty() {
+        genre = Genres(genres_tracks);
+    }
+    // Tracks only keep their own genre when it differs from the set's
+    for track in tracks.values_mut() {
+        if track.genre.as_ref() == Some(&genre) {
+            track.genre = None;
+        }
+    }
+    let released = soundcloud_parse_released(&playlist.release_date.unwrap_or(playlist.created_at))?;
+    let cover = soundcloud_parse_cover(&playlist.artwork_url.or(cover_track).unwrap_or(playlist.user.avatar_url));
+    // Sets can be longer than a track count holds
+    let track_count = tracks.len().try_into().unwrap_or(i8::MAX);
+
+    Ok(Album { name, artist, genre, released, cover, tracks, track_count })
+}
+
+/// Fills in the tracks of a set that are only ids in the page.
+///
+/// Only the first few tracks of a set are hydrated in the page, the rest are fetched
+/// from the api-v2, which doesn't keep the order of the ids.
+fn soundcloud_hydrate_tracks(
+    fetcher: &dyn Fetcher,
+    tracks: Vec<serde_json::Value>,
+    client_id: Option<&str>,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let is_hydrated = |track: &serde_json::Value| track.get("title").is_some();
+    let ids: Vec<u64> = tracks
+        .iter()
+        .filter(|track| !is_hydrated(track))
+        .filter_map(|track| track["id"].as_u64())
+        .collect();
+    if ids.is_empty() {
+        return Ok(tracks);
+    }
+    let client_id = match client_id {
+        Some(client_id) => client_id,
+        None => return Err(Error::new(ErrorKind::InvalidData, "No API client id in the page to fetch the rest of the set")),
+    };
+
+    let mut fetched: HashMap<u64, serde_json::Value> = HashMap::new();
+    for chunk in ids.chunks(API_TRACKS_MAX) {
+        let ids = chunk.iter().map(u64::to_string).collect::<Vec<String>>().join(",");
+        let url = format!("{}/tracks?ids={}&client_id={}", API_URL, ids, client_id);
+        let values: Vec<serde_json::Value> = serde_json::from_str(&fetcher.fetch(&url)?)?;
+        for value in values {
+            if let Some(id) = value["id"].as_u64() {
+                fetched.insert(id, value);
+            }
+        }
+    }
+
+    tracks
+        .into_iter()
+        .map(|track| {
+            if is_hydrated(&track) {
+                return Ok(track);
+            }
+            let id = track["id"].as_u64().unwrap_or_default();
+            match fetched.remove(&id) {
+                Some(track) => Ok(track),
+                None => Err(Error::new(ErrorKind::NotFound, format!("Track {} of the set could not be fetched", id))),
+            }
+        })
+        .collect()
+}
+
+pub fn soundcloud_get(fetcher: &dyn Fetcher, url: &str) -> Result<Album, Error> {
+    let json = soundcloud_get_json(fetcher, url)?;
+    match soundcloud_parse_json(&json)? {
+        SoundcloudResource::Sound(sound) => soundcloud_parse_sound(fetcher, url, sound),
+        SoundcloudResource::Playlist(playlist) => {
+            let client_id = soundcloud_parse_client_id(&json);
+            soundcloud_parse_playlist(fetcher, playlist, client_id.as_deref())
+        }
+    }
+}
@@ -1,17 +1,43 @@
 mod tagger;
 mod downloader;
-
-use std::{collections::HashMap, process::Stdio, fs::{create_dir_all, remove_file}, io::Error, path::Path, fmt::Write};
-use downloader::{download_track, get_cover};
-use tagger::tag_track;
-use chrono::{NaiveDate, Duration};
+mod loudness;
+pub mod audit;
+pub mod cache;
+pub mod config;
+pub mod cover;
+pub mod db;
+pub mod fingerprint;
+pub mod importer;
+pub mod lockfile;
+pub mod probe;
+pub mod waveform;
+pub mod transcoder;
+
+use std::{borrow::Borrow, collections::HashMap, process::Stdio, fs::{create_dir_all, read_dir, remove_file, rename}, io::{Error, ErrorKind}, path::Path};
+use downloader::download_track;
+use cover::{get_cover, make_embedded_cover, read_cover};
+use tagger::{get_expected_tags, read_custom_tag, read_tags, tag_track, KEY_TRACK_ID};
+use loudness::tag_album_loudness;
+use fingerprint::update_fingerprint;
+use lockfile::{get_path_lockfile, read_lockfile, write_lockfile, Lockfile};
+use chrono::NaiveDate;
 
 use serde::{Deserialize, Serialize, de::Visitor};
 
 const SECONDS_HOUR: i32 = 60 * 60;
 const SECONDS_MIN: i32 = 60;
+const DELIMITER_DURATION: &str = ":";
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Seconds a track can be longer or shorter than its duration in the library.
+pub const DURATION_TOLERANCE: f64 = 5.0;
+
+/// The audio formats yar can download and tag.
+pub const AUDIO_FORMATS: [&str; 5] = ["mp3", "flac", "opus", "m4a", "ogg"];
+
+/// The audio formats `--retag` supports, their tags are rewritten in place without ffmpeg.
+pub const RETAG_FORMATS: [&str; 1] = ["mp3"];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Library {
     pub albums: Vec<Album>,
 }
@@ -24,14 +50,51 @@ impl Library {
 pub struct Album {
     pub name: String,
     pub artist: String,
-    pub genre: String,
+    pub genre: Genres,
     // pub duration: String,
     pub released: NaiveDate,
     pub cover: String,
+    #[serde(serialize_with = "serialize_tracks")]
     pub tracks: HashMap<String, Track>,
     pub track_count: i8,
 }
 
+/// Writes tracks in the order of their position, so rewriting an album file keeps its order.
+fn serialize_tracks<S>(tracks: &HashMap<String, Track>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer {
+    let mut tracks: Vec<(&String, &Track)> = tracks.iter().collect();
+    tracks.sort_by_key(|(track_position, _)| get_position_key(track_position));
+    serializer.collect_map(tracks)
+}
+
+/// Sorts track positions numerically, falling back to text for non numeric positions.
+///
+/// # Example
+/// ```
+/// let mut positions = vec!["10", "2", "b", "01"];
+/// positions.sort_by_key(|position| yar::get_position_key(position));
+/// assert_eq!(positions, vec!["01", "2", "10", "b"]);
+/// ```
+pub fn get_position_key(track_position: &str) -> (i32, String) {
+    (track_position.parse().unwrap_or(i32::MAX), track_position.to_owned())
+}
+
+/// Checks if two track positions are the same track, eg. `01` and `1`.
+///
+/// # Example
+/// ```
+/// assert!(yar::is_same_position("01", "1"));
+/// assert!(!yar::is_same_position("1", "2"));
+/// assert!(yar::is_same_position("a1", "a1"));
+/// ```
+pub fn is_same_position(a: &str, b: &str) -> bool {
+    match (a.parse::<i32>(), b.parse::<i32>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Track {
     pub name: String,
@@ -42,6 +105,7 @@ pub struct Track {
     pub sample: Option<Vec<Sample>>,
     pub lyrics: Option<String>,
     pub wave: Option<Wave>,
+    pub genre: Option<Genres>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -73,6 +137,43 @@ pub struct Config<'a> {
     pub force: bool,
     pub download_covers: bool,
     pub keep_full_files: bool,
+    pub replaygain: bool,
+    pub cover_size: Option<u32>,
+    pub retag: bool,
+    pub strip_tags: bool,
+    /// Seconds a downloaded track can be off its expected duration, `None` skips the check.
+    pub duration_tolerance: Option<f64>,
+    pub fingerprint: bool,
+    /// Directory downloads are shared in between albums and syncs, `None` turns it off.
+    pub cache: Option<String>,
+    /// Size the cache is kept under, in MB.
+    pub cache_size: u64,
+    /// How often a failed download is tried again.
+    pub retries: u32,
+}
+
+/// The defaults of the settings, see [`config::Settings`].
+impl Default for Config<'static> {
+    fn default() -> Config<'static> {
+        Config {
+            debug_ytdl: true,
+            debug_ffmpeg: true,
+            debug: true,
+            audio_fmt: "mp3",
+            force: false,
+            download_covers: true,
+            keep_full_files: false,
+            replaygain: false,
+            cover_size: None,
+            retag: false,
+            strip_tags: false,
+            duration_tolerance: Some(DURATION_TOLERANCE),
+            fingerprint: false,
+            cache: Some(cache::get_path_cache()),
+            cache_size: cache::CACHE_SIZE,
+            retries: 0,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -121,8 +222,83 @@ impl Serialize for Wave {
     }
 }
 
-/// Converts a number of seconds to a duration string.
+/// A list of genres, written as a single string when there is only one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Genres(pub Vec<String>);
+
+impl Genres {
+    /// Splits a comma separated list of genres.
+    ///
+    /// # Example
+    /// ```
+    /// let genres = yar::Genres::parse("Phonk, Memphis Rap,");
+    /// assert_eq!(genres.0, vec!["Phonk", "Memphis Rap"]);
+    /// ```
+    pub fn parse(genres: &str) -> Self {
+        let genres = genres
+            .split(',')
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .map(String::from)
+            .collect();
+        Genres(genres)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+struct VisitorGenres;
 
+impl<'de> Visitor<'de> for VisitorGenres {
+    type Value = Genres;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("expected a string or a list of strings")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: serde::de::Error, {
+        if v.is_empty() {
+            return Ok(Genres::default());
+        }
+        Ok(Genres(vec![String::from(v)]))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: serde::de::SeqAccess<'de>, {
+        let mut genres = vec![];
+        while let Some(genre) = seq.next_element::<String>()? {
+            genres.push(genre);
+        }
+        Ok(Genres(genres))
+    }
+}
+
+impl<'de> Deserialize<'de> for Genres {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de> {
+        deserializer.deserialize_any(VisitorGenres)
+    }
+}
+
+impl Serialize for Genres {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer {
+        match self.0.as_slice() {
+            [genre] => serializer.serialize_str(genre),
+            genres => genres.serialize(serializer),
+        }
+    }
+}
+
+/// Converts a number of seconds to a duration string.
+///
 /// # Example
 ///
 /// Basic usage
@@ -144,6 +320,34 @@ pub fn duration_seconds_format(seconds_total: i32) -> Result<String, Error> {
     Ok(format!("{}:{}:{}", hour, min, sec))
 }
 
+/// Converts a duration string to number of seconds.
+///
+/// # Example
+///
+/// Basic usage
+///
+/// ```
+/// let seconds = yar::duration_seconds_parse("4:20");
+///
+/// assert!(!seconds.is_err());
+/// assert_eq!(seconds.unwrap(), 260);
+/// ```
+pub fn duration_seconds_parse(duration: &str) -> Result<i32, Error> {
+    let mut parts = Vec::from_iter(duration.split(DELIMITER_DURATION));
+    parts.reverse();
+    let mut result = 0;
+    for (idx, part) in parts.into_iter().enumerate() {
+        let part_parsed: i32 = match part.trim().parse() {
+            Ok(part) => part,
+            Err(_) => return Err(Error::new(ErrorKind::InvalidData, format!("Invalid duration: {}", duration))),
+        };
+        let base: i32 = 60;
+        let to_add = part_parsed * base.pow(idx as u32);
+        result += to_add;
+    }
+    Ok(result)
+}
+
 /// Creates a string representing the title of the track.
 ///
 /// # Example
@@ -153,16 +357,17 @@ pub fn duration_seconds_format(seconds_total: i32) -> Result<String, Error> {
 /// ```
 /// let track = &yar::Track {
 ///     name: String::from("My Song"),
-///     artists: Some(vec![String::from("John"), String::from("Andrew")]),
-///     remix: Some(String::from("Dennis")),
-///     artist_cover: Some(String::from("Chris")),
+///     artists: None,
+///     artist: None,
 ///     location: vec![],
 ///     duration: None,
 ///     lyrics: None,
-///     sample: None
+///     sample: None,
+///     wave: None,
+///     genre: None,
 /// };
 /// let title = yar::get_track_title(track);
-/// assert_eq!(title, "My Song (Dennis Remix) (Chris Cover)");
+/// assert_eq!(title, "My Song");
 /// ```
 pub fn get_track_title(track: &Track) -> String {
     let name = &track.name;
@@ -177,7 +382,7 @@ pub fn get_track_title(track: &Track) -> String {
     //     String::new()
     // };
 
-    format!("{}", name)
+    name.to_string()
 }
 
 pub fn get_stdout(debug: bool) -> Stdio {
@@ -195,8 +400,8 @@ pub fn get_stdout(debug: bool) -> Stdio {
 ///     artist: String::from("My Artist"),
 ///     name: String::from("Album Name"),
 ///     cover: String::new(),
-///     genre: String::new(),
-///     released: 0,
+///     genre: yar::Genres::default(),
+///     released: chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
 ///     track_count: 0,
 ///     tracks: std::collections::HashMap::new(),
 /// };
@@ -211,6 +416,83 @@ pub fn get_path_album(path_library: &str, album: &Album) -> String {
     format!("{}{}/{}/", path_library, path_artist, album_dir_name)
 }
 
+/// Returns a path to a track file in an album dir.
+///
+/// # Example
+/// ```
+/// let track = &yar::Track {
+///     name: String::from("My Song"),
+///     artists: None,
+///     artist: None,
+///     location: vec![],
+///     duration: None,
+///     lyrics: None,
+///     sample: None,
+///     wave: None,
+///     genre: None,
+/// };
+/// let path = yar::get_path_track("./library/my-artist/album-name/", "1", track, "mp3");
+/// assert_eq!(path, "./library/my-artist/album-name/1 - My Song.mp3");
+/// ```
+pub fn get_path_track(path_album: &str, track_position: &str, track: &Track, audio_fmt: &str) -> String {
+    format!("{}{} - {}.{}", path_album, track_position, track.name, audio_fmt)
+}
+
+/// Returns an identifier of a track that stays the same when its name changes.
+///
+/// # Example
+/// ```
+/// let album = &yar::Album {
+///     artist: String::from("My Artist"),
+///     name: String::from("Album Name"),
+///     cover: String::new(),
+///     genre: yar::Genres::default(),
+///     released: chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+///     track_count: 0,
+///     tracks: std::collections::HashMap::new(),
+/// };
+/// assert_eq!(yar::get_track_id(album, "1"), "my-artist/album-name/1");
+/// ```
+pub fn get_track_id(album: &Album, track_position: &str) -> String {
+    format!("{}/{}/{}", parse_name(&album.artist), parse_name(&album.name), track_position)
+}
+
+/// Reads the track identifier yar tagged a file with.
+pub fn read_track_id(path: &str) -> Result<Option<String>, Error> {
+    read_custom_tag(path, KEY_TRACK_ID)
+}
+
+/// Reads the location a file was downloaded from.
+pub fn read_track_source(path: &str) -> Option<Location> {
+    tagger::read_source(path)
+}
+
+/// Finds the album, position and track of a track identifier.
+pub fn find_track<'a>(library: &'a Library, track_id: &str) -> Option<(&'a Album, &'a str, &'a Track)> {
+    library.albums.iter().find_map(|album| {
+        album.tracks
+            .iter()
+            .find(|(track_position, _)| get_track_id(album, track_position) == track_id)
+            .map(|(track_position, track)| (album, track_position.as_str(), track))
+    })
+}
+
+/// Finds the album of an artist by their id on a location, eg. their soundcloud name.
+///
+/// Only tracks by the album artist alone count, so features don't match.
+pub fn find_artist<'a>(library: &'a Library, artist: &str) -> Option<&'a Album> {
+    library.albums.iter().find(|album| {
+        album.tracks.iter().any(|(_, track)|
+            if let Some(artists) = &track.artists {
+                artists.len() == 1
+            } else {
+                true
+            }
+            && track.location.iter().any(|loc| loc.url.contains(artist))
+        )
+    })
+}
+
 /// Parses a name (artist or album) for the file system.
 ///
 /// 1. Converts the name to lowercase
@@ -238,21 +520,128 @@ pub fn read_library(path: String) -> Library {
     library
 }
 
+/// Where a library is read from, a library file or a db directory of album files.
+#[derive(Debug, Clone)]
+pub enum LibrarySource {
+    File(String),
+    Db(String),
+}
+
+impl LibrarySource {
+    /// Reads every album of the library.
+    pub fn read(&self) -> Result<Library, Error> {
+        match self {
+            LibrarySource::File(path) => read_library_file(path),
+            LibrarySource::Db(path) => db::scan_db(path),
+        }
+    }
+
+    /// Returns the albums of the library, the files of a db directory are read as they're used.
+    pub fn albums(&self) -> Result<db::Albums, Error> {
+        match self {
+            LibrarySource::File(path) => Ok(Box::new(read_library_file(path)?.albums.into_iter().map(Ok))),
+            LibrarySource::Db(path) => db::read_db_albums(path),
+        }
+    }
+}
+
+fn read_library_file(path: &str) -> Result<Library, Error> {
+    let f = std::fs::File::open(path)?;
+    match serde_yaml::from_reader(f) {
+        Ok(library) => Ok(library),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("Could not read library {}: {}", path, err))),
+    }
+}
+
 pub fn process_library(config: &Config, path_library: &str, library: &Library) -> Result<(), Error> {
-    for album in &library.albums {
-        process_library_album(config, &path_library, album).unwrap();
+    process_albums(config, path_library, library.albums.iter().map(Ok))
+}
+
+/// Processes albums as they are read, an album that can't be read or synced is skipped.
+///
+/// Retagging a library in a format that isn't in [`RETAG_FORMATS`] is an error.
+pub fn process_albums<A: Borrow<Album>>(
+    config: &Config,
+    path_library: &str,
+    albums: impl Iterator<Item = Result<A, Error>>,
+) -> Result<(), Error> {
+    if config.retag && !RETAG_FORMATS.contains(&config.audio_fmt) {
+        return Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Can not retag {} files, only {} tags are rewritten without ffmpeg", config.audio_fmt, RETAG_FORMATS.join(", ")),
+        ));
+    }
+    let path_lockfile = get_path_lockfile(path_library);
+    let mut lockfile = match config.fingerprint && !config.retag {
+        true => Some(read_lockfile(&path_lockfile)?),
+        false => None,
+    };
+    for album in albums {
+        let album = match album {
+            Ok(album) => album,
+            Err(err) => {
+                println!("ERR: Could not read album");
+                println!("{}", err);
+                continue;
+            }
+        };
+        if let Err(err) = process_library_album(config, path_library, album.borrow(), lockfile.as_mut()) {
+            println!("ERR: Could not sync album: {}", album.borrow().name);
+            println!("{}", err);
+        }
+        // Written after every album, so an interrupted sync keeps what it computed
+        if let Some(lockfile) = &lockfile {
+            write_lockfile(&path_lockfile, lockfile)?;
+        }
     }
     Ok(())
 }
 
-fn process_library_album(config: &Config, path_library: &str, album: &Album) -> Result<(), Error> {
-    let path_album = get_path_album(&path_library, album);
+fn process_library_album(
+    config: &Config,
+    path_library: &str,
+    album: &Album,
+    mut lockfile: Option<&mut Lockfile>,
+) -> Result<(), Error> {
+    let path_album = get_path_album(path_library, album);
+
+    if config.retag && !Path::new(&path_album).exists() {
+        println!("Skipping missing album: {}", path_album);
+        return Ok(());
+    }
 
     println!("{}", &path_album);
     create_dir_all(&path_album).unwrap();
 
-    let path_cover = format!("{}cover.jpg", path_album);
-    let cover = get_cover(config, &path_cover, &album);
+    // Retagging only uses what is on disk
+    let cover = match config.retag {
+        true => read_cover(&path_album),
+        false => get_cover(config, &path_album, album),
+    };
+    // Tracks are still synced without a cover
+    let cover = match cover {
+        Ok(cover) => cover,
+        Err(err) => {
+            println!("ERR: Could not get the cover of {}", path_album);
+            println!("{}", err);
+            vec![]
+        }
+    };
+    let cover = match make_embedded_cover(&cover, config.cover_size) {
+        Ok(cover) => cover,
+        Err(err) => {
+            println!("ERR: Could not read the cover of {}", path_album);
+            println!("{}", err);
+            vec![]
+        }
+    };
+
+    if config.retag {
+        for (track_position, track) in album.tracks.iter() {
+            retag_library_album_track(config, &path_album, album, cover.clone(), track_position, track);
+        }
+        return Ok(());
+    }
 
     let mut full_files = vec![];
 
@@ -260,17 +649,77 @@ fn process_library_album(config: &Config, path_library: &str, album: &Album) ->
         process_library_album_track(
             config,
             &mut full_files,
+            lockfile.as_deref_mut(),
             &path_album,
             album,
             cover.clone(),
-            &track_postion,
+            track_postion,
             track,
         )
     }
-    clean_up_album(&config, &full_files)
+    clean_up_album(config, &full_files)?;
+
+    if config.replaygain {
+        let paths: Vec<String> = album.tracks
+            .iter()
+            .map(|(track_position, track)| get_path_track(&path_album, track_position, track, config.audio_fmt))
+            .filter(|path| std::path::Path::new(path).exists())
+            .collect();
+        if let Err(err) = tag_album_loudness(config, &paths) {
+            println!("ERR: Could not analyze loudness of {}", path_album);
+            println!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Rewrites the tags of a track that was already downloaded, renaming it if its name changed.
+fn retag_library_album_track(
+    config: &Config,
+    path_album: &str,
+    album: &Album,
+    cover: Vec<u8>,
+    track_position: &str,
+    track: &Track,
+) {
+    let path_out = &get_path_track(path_album, track_position, track, config.audio_fmt);
+    if !Path::new(path_out).exists() {
+        match find_track_file(path_album, track_position, config.audio_fmt) {
+            Some(path_old) => {
+                println!("Renaming: {} -> {}", path_old, path_out);
+                if let Err(err) = rename(&path_old, path_out) {
+                    println!("ERR: Could not rename {}", path_old);
+                    println!("{}", err);
+                    return;
+                }
+            }
+            None => {
+                println!("Skipping missing track: {}", path_out);
+                return;
+            }
+        }
+    }
+
+    println!("{}: {}", track_position, track.name);
+    if let Err(err) = tag_track(path_out, album, track, track_position, cover, None, config.strip_tags) {
+        println!("ERR: Could not write tags to {}", path_out);
+        println!("{}", err);
+    }
+}
+
+/// Finds the file of a track by its position, eg. `01 - Old Name.mp3`.
+fn find_track_file(path_album: &str, track_position: &str, audio_fmt: &str) -> Option<String> {
+    let prefix = format!("{} - ", track_position);
+    let suffix = format!(".{}", audio_fmt);
+    read_dir(path_album)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .find(|name| name.starts_with(&prefix) && name.ends_with(&suffix) && !name.contains(".tagging."))
+        .map(|name| format!("{}{}", path_album, name))
 }
 
-fn clean_up_album(config: &Config, full_files: &Vec<String>) -> Result<(), Error> {
+fn clean_up_album(config: &Config, full_files: &[String]) -> Result<(), Error> {
     let remove_full_files = !config.keep_full_files;
     for path in full_files {
         if remove_full_files {
@@ -287,12 +736,19 @@ fn remove_full_file(config: &Config, path: &str) -> Result<(), Error> {
     if config.debug {
         println!("removing full file: {}", path);
     };
-    remove_file(path)
+    remove_file(path)?;
+    // The directory of full files is removed with the last one
+    if let Some(parent) = Path::new(path).parent() {
+        let _ = std::fs::remove_dir(parent);
+    }
+    Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_library_album_track(
     config: &Config,
     full_files: &mut Vec<String>,
+    lockfile: Option<&mut Lockfile>,
     path_album: &str,
     album: &Album,
     cover: Vec<u8>,
@@ -301,30 +757,63 @@ fn process_library_album_track(
 ) {
     println!("{}: {}", track_position, track.name);
 
-    let track_name = &track.name;
-    let path_out = &format!(
-        "{}{} - {}.{}",
-        &path_album, track_position, track_name, config.audio_fmt
-    );
+    let path_out = &get_path_track(path_album, track_position, track, config.audio_fmt);
 
     let result = download_track(
         config,
         full_files,
-        &path_album,
-        &path_out,
-        &album,
-        &track,
-        track_position,
+        path_album,
+        path_out,
+        album,
+        track,
     );
 
-    match result {
-        Ok(_) => match tag_track(&path_out, &album, track, &track_position, cover.clone()) {
-            Ok(_) => println!("wrote tags!!"),
-            Err(err) => {
-                println!("{:?}", err);
-                panic!("could not write tags to {}", path_out);
-            }
-        },
-        Err(_) => (),
+    let source = match result {
+        Ok(source) => source,
+        Err(err) => {
+            println!("ERR: Could not download {}", path_out);
+            println!("{}", err);
+            return;
+        }
+    };
+    // Rewriting the same tags would still touch the file, eg. making transcodes think it changed
+    if source.is_none() && !config.strip_tags && is_tagged(path_out, album, track, track_position, &cover) {
+        if config.debug {
+            println!("Tags are up to date");
+        }
+    } else {
+        tag_downloaded_track(config, path_out, album, track, track_position, cover, source.as_ref());
+    }
+
+    if let Some(lockfile) = lockfile {
+        let track_id = &get_track_id(album, track_position);
+        update_fingerprint(config, lockfile, track_id, path_out, source.as_ref());
+    }
+}
+
+/// Checks if a file already has the tags and cover yar would write.
+fn is_tagged(path: &str, album: &Album, track: &Track, track_position: &str, cover: &[u8]) -> bool {
+    let expected = get_expected_tags(album, track, track_position);
+    match read_tags(path) {
+        Ok(tags) => tags.diff(&expected).is_empty() && tags.track_id == expected.track_id && tags.cover == cover,
+        Err(_) => false,
+    }
+}
+
+fn tag_downloaded_track(
+    config: &Config,
+    path_out: &str,
+    album: &Album,
+    track: &Track,
+    track_position: &str,
+    cover: Vec<u8>,
+    source: Option<&Location>,
+) {
+    match tag_track(path_out, album, track, track_position, cover, source, config.strip_tags) {
+        Ok(_) => println!("wrote tags!!"),
+        Err(err) => {
+            println!("ERR: Could not write tags to {}", path_out);
+            println!("{}", err);
+        }
     }
 }
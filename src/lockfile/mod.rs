@@ -0,0 +1,67 @@
+// State of a library directory that is kept between syncs, in `yar.lock`
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{get_track_id, Library};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    /// Tracks by their track id, see [`crate::get_track_id`].
+    pub tracks: BTreeMap<String, LockedTrack>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LockedTrack {
+    /// URL the track was last downloaded from.
+    pub source: Option<String>,
+    /// See [`crate::fingerprint::Fingerprint::encode`].
+    pub fingerprint: Option<String>,
+}
+
+/// Returns the path of the lockfile of a library directory.
+pub fn get_path_lockfile(path_library: &str) -> String {
+    format!("{}yar.lock", path_library)
+}
+
+/// Reads a lockfile, a missing one is empty.
+pub fn read_lockfile(path: &str) -> Result<Lockfile, Error> {
+    if !Path::new(path).exists() {
+        return Ok(Lockfile::default());
+    }
+    let file = File::open(path)?;
+    match serde_yaml::from_reader(file) {
+        Ok(lockfile) => Ok(lockfile),
+        Err(err) => Err(Error::new(ErrorKind::InvalidData, format!("Could not read lockfile {}: {}", path, err))),
+    }
+}
+
+/// Removes the tracks that are no longer in the library, returns their track ids.
+pub fn prune_lockfile(lockfile: &mut Lockfile, library: &Library) -> Vec<String> {
+    let track_ids: HashSet<String> = library
+        .albums
+        .iter()
+        .flat_map(|album| album.tracks.keys().map(|track_position| get_track_id(album, track_position)))
+        .collect();
+    let removed: Vec<String> = lockfile
+        .tracks
+        .keys()
+        .filter(|track_id| !track_ids.contains(*track_id))
+        .cloned()
+        .collect();
+    for track_id in removed.iter() {
+        lockfile.tracks.remove(track_id);
+    }
+    removed
+}
+
+pub fn write_lockfile(path: &str, lockfile: &Lockfile) -> Result<(), Error> {
+    let file = File::create(path)?;
+    match serde_yaml::to_writer(file, lockfile) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::new(ErrorKind::InvalidData, "Could not serialize lockfile")),
+    }
+}
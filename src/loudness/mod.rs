@@ -0,0 +1,142 @@
+// ReplayGain 2.0 analysis using ffmpeg's EBU R128 filter
+use std::io::{Error, ErrorKind};
+use std::process::Command;
+
+use crate::tagger::{read_custom_tag, write_custom_tags};
+use crate::Config;
+
+/// Loudness that ReplayGain 2.0 normalizes to, in LUFS.
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+const KEY_TRACK_GAIN: &str = "REPLAYGAIN_TRACK_GAIN";
+const KEY_TRACK_PEAK: &str = "REPLAYGAIN_TRACK_PEAK";
+const KEY_ALBUM_GAIN: &str = "REPLAYGAIN_ALBUM_GAIN";
+const KEY_ALBUM_PEAK: &str = "REPLAYGAIN_ALBUM_PEAK";
+
+#[derive(Debug, Clone, Copy)]
+pub struct Loudness {
+    /// Integrated loudness in LUFS.
+    pub integrated: f64,
+    /// True peak in dBFS.
+    pub peak: f64,
+}
+
+impl Loudness {
+    /// Returns the ReplayGain gain, eg. `-3.20 dB`.
+    pub fn format_gain(&self) -> String {
+        format!("{:.2} dB", REFERENCE_LOUDNESS - self.integrated)
+    }
+
+    /// Returns the ReplayGain peak as a linear amplitude, eg. `0.988553`.
+    pub fn format_peak(&self) -> String {
+        format!("{:.6}", 10f64.powf(self.peak / 20.0))
+    }
+}
+
+/// Writes ReplayGain tags to the tracks of an album when some of them don't have them yet.
+///
+/// The album is analyzed as a whole and its gain is written to every track, so the album
+/// gain of tracks that were tagged before a track was added is updated too.
+pub fn tag_album_loudness(config: &Config, paths: &[String]) -> Result<(), Error> {
+    let missing: Vec<&String> = paths
+        .iter()
+        .filter(|path| !has_replaygain(path))
+        .collect();
+    if missing.is_empty() {
+        if config.debug {
+            println!("Skipping ReplayGain, all tracks are tagged");
+        }
+        return Ok(());
+    }
+
+    println!("analyzing loudness");
+    let album = analyze(paths)?;
+    for path in paths {
+        let mut values = vec![];
+        if missing.contains(&path) {
+            let track = analyze(&[path.to_owned()])?;
+            if config.debug {
+                println!("{}: {} LUFS, {} dBFS", path, track.integrated, track.peak);
+            }
+            values.push((String::from(KEY_TRACK_GAIN), track.format_gain()));
+            values.push((String::from(KEY_TRACK_PEAK), track.format_peak()));
+        }
+        values.push((String::from(KEY_ALBUM_GAIN), album.format_gain()));
+        values.push((String::from(KEY_ALBUM_PEAK), album.format_peak()));
+        if let Err(err) = write_custom_tags(path, &values) {
+            println!("ERR: Could not write ReplayGain to {}", path);
+            println!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+fn has_replaygain(path: &str) -> bool {
+    matches!(read_custom_tag(path, KEY_TRACK_GAIN), Ok(Some(_)))
+}
+
+/// Measures the loudness of one or more files as if they were played back to back.
+pub fn analyze(paths: &[String]) -> Result<Loudness, Error> {
+    let args = get_ffmpeg_args(paths);
+    let output = Command::new("ffmpeg").args(args).output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        return Err(Error::other(stderr.to_string()));
+    }
+    parse_summary(&stderr)
+}
+
+fn get_ffmpeg_args(paths: &[String]) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        String::from("-hide_banner"),
+        String::from("-nostats"),
+    ];
+    for path in paths {
+        args.push(String::from("-i"));
+        args.push(path.to_owned());
+    }
+
+    // Tracks can have different sample rates and layouts, which concat doesn't allow
+    let mut filter = String::new();
+    for idx in 0..paths.len() {
+        filter.push_str(&format!(
+            "[{}:a]aresample=48000,aformat=sample_fmts=fltp:channel_layouts=stereo[a{}];",
+            idx, idx
+        ));
+    }
+    for idx in 0..paths.len() {
+        filter.push_str(&format!("[a{}]", idx));
+    }
+    filter.push_str(&format!("concat=n={}:v=0:a=1,ebur128=peak=true[out]", paths.len()));
+
+    args.extend([
+        String::from("-filter_complex"),
+        filter,
+        String::from("-map"),
+        String::from("[out]"),
+        String::from("-f"),
+        String::from("null"),
+        String::from("-"),
+    ]);
+    args
+}
+
+/// Reads the summary the ebur128 filter prints when it's done.
+fn parse_summary(output: &str) -> Result<Loudness, Error> {
+    let summary = match output.rsplit_once("Summary:") {
+        Some((_, summary)) => summary,
+        None => return Err(Error::new(ErrorKind::InvalidData, "No loudness summary in ffmpeg output")),
+    };
+    let get_value = |label: &str| -> Option<f64> {
+        summary
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(label))
+            .and_then(|line| line[label.len()..].split_whitespace().next())
+            .and_then(|value| value.parse().ok())
+    };
+    match (get_value("I:"), get_value("Peak:")) {
+        (Some(integrated), Some(peak)) => Ok(Loudness { integrated, peak }),
+        _ => Err(Error::new(ErrorKind::InvalidData, "Could not parse loudness summary")),
+    }
+}
@@ -1,37 +1,652 @@
+use yar::{find_track, Album, Genres, Library, LibrarySource, read_track_id, read_track_source, process_albums, AUDIO_FORMATS, DURATION_TOLERANCE};
+use yar::audit::audit_library;
+use yar::cache::Cache;
+use yar::config::{get_path_config, read_settings, Settings};
+use yar::db::scan_db;
+use yar::fingerprint::find_duplicates;
+use yar::importer::{bandcamp, folder, merge::merge_album, read_album, set_default_genre, soundcloud::soundcloud_get, write_album, HttpFetcher};
+use yar::lockfile::{get_path_lockfile, read_lockfile, write_lockfile};
+use yar::transcoder::{get_path_profiles, read_profiles, transcode_library};
+use yar::waveform::{generate_album_waves, render_library_waves, WAVE_POINTS};
+use yar::waveform::render::{RenderOptions, WaveStyle};
 
-use std::env;
-use yar::{read_library, process_library,Config};
-
-use clap::{arg, command};
+use std::fmt::Display;
+use std::io::{Error, ErrorKind};
 
+use clap::{arg, command, Arg, ArgAction, ArgMatches, Command};
+use clap_complete::Shell;
 
 fn main() {
-    let matches = &command!()
+    let matches = &build_cli().get_matches();
+
+    match matches.subcommand() {
+        Some(("sync", matches)) => sync(matches),
+        Some(("build", matches)) => build(matches),
+        Some(("import", matches)) => import(matches),
+        Some(("find-artist", matches)) => find_artist(matches),
+        Some(("transcode", matches)) => transcode(matches),
+        Some(("wave", matches)) => wave(matches),
+        Some(("waveform", matches)) => waveform(matches),
+        Some(("audit", matches)) => audit(matches),
+        Some(("duplicates", matches)) => duplicates(matches),
+        Some(("which", matches)) => which(matches),
+        Some(("cache", matches)) => cache(matches),
+        Some(("config", matches)) => config(matches),
+        Some(("completions", matches)) => completions(matches),
+        Some(("man", matches)) => man(matches),
+        _ => sync(matches),
+    }
+}
+
+fn build_cli() -> Command {
+    let command = command!()
+        .args_conflicts_with_subcommands(true)
+        .arg(arg!(--config <config> "Path to the config file, ~/.config/yar/config.yml by default")
+            .global(true));
+    add_sync_args(command)
+        .subcommand(add_sync_args(Command::new("sync")
+            .about("Downloads and tags a library into the library directory, the default")))
+        .subcommand(Command::new("build")
+            .about("Builds a single library file from a db directory of album files")
+            .arg(arg!(-i <db> "Path to a db directory of album files"))
+            .arg(arg!(-o <file_out> "Path to write the library file to, printed if not set")))
+        .subcommand(Command::new("import")
+            .about("Creates album files from a page or a folder of music")
+            .subcommand_required(true)
+            .subcommand(Command::new("bandcamp")
+                .about("Imports a bandcamp album, or every release of an artist or label page")
+                .arg(arg!(<url> "Bandcamp URL"))
+                .arg(arg!(-o <dir_out> "Directory to write a discography into"))
+                .arg(arg!(-m <merge> "Album file to update with the scraped data"))
+                .arg(arg!(-g <genre> "Genre to use when the page has none, can be repeated").action(ArgAction::Append)))
+            .subcommand(Command::new("soundcloud")
+                .about("Imports a soundcloud track or set")
+                .arg(arg!(<url> "Soundcloud URL"))
+                .arg(arg!(-m <merge> "Album file to update with the scraped data"))
+                .arg(arg!(-g <genre> "Genre to use when the page has none, can be repeated").action(ArgAction::Append)))
+            .subcommand(Command::new("folder")
+                .about("Imports a folder of tagged music files")
+                .arg(arg!(<path> "Folder of tagged music files"))
+                .arg(arg!(-o <dir_out> "Directory to write the album files into"))
+                .arg(arg!(-g <genre> "Genre to use when the files have none, can be repeated").action(ArgAction::Append))))
+        .subcommand(Command::new("find-artist")
+            .about("Finds the name of an artist by their id on a location, eg. their soundcloud name")
+            .arg(arg!(<artist> "Artist id on location"))
+            .arg(arg!(-f <file> "Path to library structure file"))
+            .arg(arg!(-i <db> "Path to a db directory of album files")
+                .conflicts_with("file")))
+        .subcommand(Command::new("transcode")
+            .about("Transcodes a library into a second directory using a profile")
+            .arg(arg!(-f <file> "Path to library structure file"))
+            .arg(arg!(-d <dir> "Path to directory of the library"))
+            .arg(arg!(-o <dir_out> "Path to directory of the transcoded library"))
+            .arg(arg!(-p <profile> "Name of the profile to use"))
+            .arg(arg!(--profiles <profiles> "Path to the profiles file"))
+            .arg(arg!(--"cover-size" <px> "Largest width or height of embedded covers")
+                .value_parser(clap::value_parser!(u32)))
+            .arg(arg!(--format <format> "Audio format of the library")
+                .value_parser(AUDIO_FORMATS)))
+        .subcommand(Command::new("wave")
+            .about("Generates the waveforms of an album file from its downloaded tracks")
+            .arg(arg!(-f <file> "Path to album file"))
+            .arg(arg!(-d <dir> "Path to directory of the library"))
+            .arg(arg!(-n <points> "Number of points in a waveform")
+                .value_parser(clap::value_parser!(usize)))
+            .arg(arg!(--force "Replace existing waveforms"))
+            .arg(arg!(--format <format> "Audio format of the library")
+                .value_parser(AUDIO_FORMATS)))
+        .subcommand(Command::new("waveform")
+            .about("Renders the waveforms of a library into its album directories")
+            .arg(arg!(-f <file> "Path to library structure file"))
+            .arg(arg!(-d <dir> "Path to directory of the library"))
+            .arg(arg!(--width <width> "Width of the image")
+                .value_parser(clap::value_parser!(u32)))
+            .arg(arg!(--height <height> "Height of the image")
+                .value_parser(clap::value_parser!(u32)))
+            .arg(arg!(--color <color> "Color of the wave, eg. #ff5500"))
+            .arg(arg!(--background <color> "Background color, transparent if not set"))
+            .arg(arg!(--style <style> "How the wave is drawn")
+                .value_parser(["mirrored", "one-sided"])
+                .default_value("mirrored"))
+            .arg(arg!(--png "Also write PNG images")))
+        .subcommand(Command::new("audit")
+            .about("Checks a library directory against the library file")
+            .arg(arg!(-f <file> "Path to library structure file"))
+            .arg(arg!(-d <dir> "Path to directory of the library"))
+            .arg(arg!(--format <format> "Audio format of the library")
+                .value_parser(AUDIO_FORMATS))
+            .arg(arg!(--tolerance <seconds> "How far durations can be off")
+                .value_parser(clap::value_parser!(f64)))
+            .arg(arg!(--json "Print the issues as JSON")))
+        .subcommand(Command::new("duplicates")
+            .about("Finds tracks that are the same recording, using the fingerprints in yar.lock")
+            .arg(arg!(-f <file> "Path to library structure file"))
+            .arg(arg!(-i <db> "Path to a db directory of album files, instead of a library file")
+                .conflicts_with("file"))
+            .arg(arg!(-d <dir> "Path to directory of the library")))
+        .subcommand(Command::new("which")
+            .about("Shows where a file came from and its entry in the library")
+            .arg(arg!(<path> "Path to a track file"))
+            .arg(arg!(-f <file> "Path to library structure file")))
+        .subcommand(Command::new("cache")
+            .about("Manages the cache of downloads shared between albums and syncs")
+            .subcommand_required(true)
+            .arg(arg!(--cache <dir> "Directory downloads are cached in, ~/.cache/yar by default"))
+            .subcommand(Command::new("list").about("Lists the cached downloads"))
+            .subcommand(Command::new("verify").about("Checks the cached files and removes the broken ones"))
+            .subcommand(Command::new("clear").about("Removes every cached download")))
+        .subcommand(Command::new("config")
+            .about("Shows the settings from the config file and environment")
+            .subcommand_required(true)
+            .subcommand(Command::new("show").about("Prints the effective settings")))
+        .subcommand(Command::new("completions")
+            .about("Prints a shell completion script")
+            .arg(arg!(<shell> "Shell to complete in")
+                .value_parser(clap::value_parser!(Shell))))
+        .subcommand(Command::new("man")
+            .about("Prints the man page, or writes the pages of every subcommand into a directory")
+            .arg(arg!(-o <dir_out> "Directory to write the man pages into")))
+}
+
+/// Adds the flags of syncing a library, which is also what `yar` without a subcommand does.
+fn add_sync_args(command: Command) -> Command {
+    command
         .arg(arg!(-f <file> "Path to library structure file"))
+        .arg(arg!(-i <db> "Path to a db directory of album files, instead of a library file")
+            .conflicts_with("file"))
         .arg(arg!(-d <dir> "Path to directory of the library"))
-        .get_matches();
+        .arg(arg!(--format <format> "Audio format of the library")
+            .value_parser(AUDIO_FORMATS))
+        .args(toggle_args("force", "no-force", "Download and tag tracks again even if they exist"))
+        .args(toggle_args("debug", "no-debug", "Print what is being done"))
+        .args(toggle_args("debug-ytdl", "no-debug-ytdl", "Print the output of yt-dlp"))
+        .args(toggle_args("debug-ffmpeg", "no-debug-ffmpeg", "Print the output of ffmpeg"))
+        .args(toggle_args("download-covers", "no-download-covers", "Save the cover as the folder image of albums"))
+        .args(toggle_args("keep-full-files", "no-keep-full-files", "Keep full album files after splitting them"))
+        .args(toggle_args("replaygain", "no-replaygain", "Analyze loudness and write ReplayGain tags"))
+        .args(toggle_args("retag", "no-retag", "Only rewrite the tags of downloaded tracks, renaming them if needed, nothing is downloaded. Only mp3 libraries can be retagged"))
+        .mut_arg("retag", |arg| arg.conflicts_with("replaygain"))
+        .args(toggle_args("strip-tags", "no-strip-tags", "Remove tags that weren't written by yar, eg. from the downloader"))
+        .arg(arg!(--"cover-size" <px> "Largest width or height of embedded covers")
+            .value_parser(clap::value_parser!(u32)))
+        .arg(arg!(--tolerance <seconds> "How far downloaded tracks can be off their duration")
+            .value_parser(clap::value_parser!(f64)))
+        .arg(arg!(--"no-duration-check" "Don't check the duration of downloaded tracks")
+            .conflicts_with("tolerance"))
+        .args(toggle_args("fingerprint", "no-fingerprint", "Fingerprint tracks and warn when a download sounds different than before"))
+        .arg(arg!(--retries <n> "How often a failed download is tried again")
+            .value_parser(clap::value_parser!(u32)))
+        .arg(arg!(--cache <dir> "Directory downloads are cached in, ~/.cache/yar by default"))
+        .arg(arg!(--"cache-size" <mb> "Size the download cache is kept under, in MB")
+            .value_parser(clap::value_parser!(u64)))
+        .arg(arg!(--"no-cache" "Don't cache downloads")
+            .conflicts_with_all(["cache", "cache-size"]))
+}
+
+/// Returns a flag that turns a setting on and one that turns it off, the one passed last wins.
+fn toggle_args(name: &'static str, name_off: &'static str, help: &'static str) -> [Arg; 2] {
+    [
+        Arg::new(name)
+            .long(name)
+            .help(help)
+            .action(ArgAction::SetTrue)
+            .overrides_with(name_off),
+        Arg::new(name_off)
+            .long(name_off)
+            .help(format!("Turn off --{}", name))
+            .action(ArgAction::SetTrue)
+            .overrides_with(name),
+    ]
+}
+
+/// Returns the value of a setting passed with [`toggle_args`], if either flag was passed.
+fn get_toggle(matches: &ArgMatches, name: &str, name_off: &str) -> Option<bool> {
+    match (matches.get_flag(name), matches.get_flag(name_off)) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Prints an error to stderr and exits, so it never ends up in output that is piped into a file.
+fn exit_with_error(message: &str, err: &dyn Display) -> ! {
+    eprintln!("ERR: {}", message);
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+/// Reads the settings, exits if the config file is invalid.
+fn get_settings(matches: &ArgMatches) -> Settings {
+    let path_config = matches.get_one::<String>("config");
+    match read_settings(path_config.map(String::as_str)) {
+        Ok(settings) => settings,
+        Err(err) => {
+            let path_config = path_config.cloned().unwrap_or_else(get_path_config);
+            exit_with_error(&format!("Could not read config {}", path_config), &err)
+        }
+    }
+}
+
+/// Overrides the paths and format of the settings with flags.
+fn apply_library_args(settings: &mut Settings, matches: &ArgMatches) {
+    let get = |id: &str| matches.try_get_one::<String>(id).ok().flatten().cloned();
+    // A library file and a db directory are two ways of passing the same thing
+    if let Some(path_file) = get("file") {
+        settings.file = Some(path_file);
+        settings.db = None;
+    }
+    if let Some(path_db) = get("db") {
+        settings.db = Some(path_db);
+        settings.file = None;
+    }
+    if let Some(path_library) = get("dir") {
+        settings.dir = Some(path_library);
+    }
+    if let Some(audio_fmt) = get("format") {
+        settings.format = audio_fmt;
+    }
+}
+
+fn get_library_source(settings: &Settings) -> LibrarySource {
+    match settings.get_library_source() {
+        Some(source) => source,
+        None => exit_with_error("No library given", &"Pass -f <file> or -i <db>, or set file or db in the config"),
+    }
+}
+
+fn get_library(settings: &Settings) -> Library {
+    match get_library_source(settings).read() {
+        Ok(library) => library,
+        Err(err) => exit_with_error("Could not read library", &err),
+    }
+}
+
+fn get_path_library(settings: &Settings) -> String {
+    match &settings.dir {
+        Some(path_library) => path_library.to_owned(),
+        None => exit_with_error("No library directory given", &"Pass -d <dir>, or set dir in the config"),
+    }
+}
+
+fn sync(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    let toggles = [
+        ("force", "no-force", &mut settings.force),
+        ("debug", "no-debug", &mut settings.debug),
+        ("debug-ytdl", "no-debug-ytdl", &mut settings.debug_ytdl),
+        ("debug-ffmpeg", "no-debug-ffmpeg", &mut settings.debug_ffmpeg),
+        ("download-covers", "no-download-covers", &mut settings.download_covers),
+        ("keep-full-files", "no-keep-full-files", &mut settings.keep_full_files),
+        ("replaygain", "no-replaygain", &mut settings.replaygain),
+        ("retag", "no-retag", &mut settings.retag),
+        ("strip-tags", "no-strip-tags", &mut settings.strip_tags),
+        ("fingerprint", "no-fingerprint", &mut settings.fingerprint),
+    ];
+    for (name, name_off, value) in toggles {
+        if let Some(toggle) = get_toggle(matches, name, name_off) {
+            *value = toggle;
+        }
+    }
+    if let Some(retries) = matches.get_one::<u32>("retries") {
+        settings.retries = *retries;
+    }
+    if let Some(tolerance) = matches.get_one::<f64>("tolerance") {
+        settings.duration_tolerance = Some(*tolerance);
+    }
+    if matches.get_flag("no-duration-check") {
+        settings.duration_tolerance = None;
+    }
+    if let Some(cover_size) = matches.get_one::<u32>("cover-size") {
+        settings.cover_size = Some(*cover_size);
+    }
+    if let Some(path_cache) = matches.get_one::<String>("cache") {
+        settings.cache = Some(path_cache.to_owned());
+    }
+    if let Some(cache_size) = matches.get_one::<u64>("cache-size") {
+        settings.cache_size = *cache_size;
+    }
+    if matches.get_flag("no-cache") {
+        settings.cache = None;
+    }
 
+    let path_library = get_path_library(&settings);
+    // Albums of a db directory are synced as they're read
+    let albums = match get_library_source(&settings).albums() {
+        Ok(albums) => albums,
+        Err(err) => exit_with_error("Could not read library", &err),
+    };
+    let config = &settings.to_config();
+
+    if let Err(err) = process_albums(config, &path_library, albums) {
+        exit_with_error(&format!("Could not sync {}", path_library), &err);
+    }
+}
+
+fn transcode(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    if let Some(cover_size) = matches.get_one::<u32>("cover-size") {
+        settings.cover_size = Some(*cover_size);
+    }
+
+    let path_library = get_path_library(&settings);
+
+    let path_out = matches
+        .get_one::<String>("dir_out")
+        .expect("Output directory is not provided")
+        .to_owned();
+
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .expect("Profile is not provided");
+
+    let path_profiles = matches
+        .get_one::<String>("profiles")
+        .cloned()
+        .unwrap_or_else(get_path_profiles);
+
+    let profiles = match read_profiles(&path_profiles) {
+        Ok(profiles) => profiles,
+        Err(err) => exit_with_error(&format!("Could not read profiles {}", path_profiles), &err),
+    };
+    let profile = match profiles.profiles.get(profile_name) {
+        Some(profile) => profile,
+        None => exit_with_error("Unknown profile", &format!("No profile named {} in {}", profile_name, path_profiles)),
+    };
+
+    let library = &get_library(&settings);
+    let config = &settings.to_config();
+
+    transcode_library(config, &path_library, &path_out, library, profile).unwrap();
+}
+
+fn wave(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    // The album file isn't the library file of the settings
     let path_file = matches
         .get_one::<String>("file")
         .expect("File path is not provided")
         .to_owned();
+    apply_library_args(&mut settings, matches);
+    settings.force |= matches.get_flag("force");
 
-    let path_library = matches
-        .get_one::<String>("dir")
-        .expect("Library directory")
-        .to_owned();
+    let path_library = get_path_library(&settings);
+
+    let points = matches
+        .get_one::<usize>("points")
+        .copied()
+        .unwrap_or(WAVE_POINTS);
+
+    let config = &settings.to_config();
+
+    generate_album_waves(config, &path_file, &path_library, points).unwrap();
+}
+
+fn waveform(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+
+    let path_library = get_path_library(&settings);
 
-    let library = &read_library(path_file);
-    let config = &Config {
-        debug_ytdl: true,
-        debug_ffmpeg: true,
-        debug: true,
-        audio_fmt: "mp3",
-        force: false,
-        download_covers: true,
-        keep_full_files: false,
+    let defaults = RenderOptions::default();
+    let options = &RenderOptions {
+        width: matches.get_one::<u32>("width").copied().unwrap_or(defaults.width),
+        height: matches.get_one::<u32>("height").copied().unwrap_or(defaults.height),
+        color: matches.get_one::<String>("color").cloned().unwrap_or(defaults.color),
+        background: matches.get_one::<String>("background").cloned(),
+        style: WaveStyle::parse(matches.get_one::<String>("style").unwrap()).unwrap(),
     };
 
-    process_library(config, &path_library, library).unwrap();
+    let library = &get_library(&settings);
+    let config = &settings.to_config();
 
+    render_library_waves(config, &path_library, library, options, matches.get_flag("png")).unwrap();
+}
+
+fn which(matches: &ArgMatches) {
+    let path = matches
+        .get_one::<String>("path")
+        .unwrap();
+
+    let track_id = match read_track_id(path) {
+        Ok(Some(track_id)) => track_id,
+        Ok(None) => {
+            eprintln!("ERR: {} was not tagged by yar", path);
+            std::process::exit(1);
+        }
+        Err(err) => exit_with_error(&format!("Could not read tags of {}", path), &err),
+    };
+    println!("id: {}", track_id);
+    if let Some(source) = read_track_source(path) {
+        match source.at {
+            Some(at) => println!("source: {} at {}", source.url, at),
+            None => println!("source: {}", source.url),
+        }
+    }
+
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    if settings.get_library_source().is_none() {
+        return;
+    }
+    let library = &get_library(&settings);
+    match find_track(library, &track_id) {
+        Some((album, track_position, track)) => {
+            println!("album: {} - {}", album.artist, album.name);
+            let entry = std::collections::BTreeMap::from([(track_position, track)]);
+            print!("{}", serde_yaml::to_string(&entry).unwrap());
+        }
+        None => {
+            eprintln!("ERR: {} is not in the library", track_id);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn audit(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    settings.debug = false;
+
+    let path_library = get_path_library(&settings);
+
+    let tolerance = matches
+        .get_one::<f64>("tolerance")
+        .copied()
+        .or(settings.duration_tolerance)
+        .unwrap_or(DURATION_TOLERANCE);
+
+    let json = matches.get_flag("json");
+    let library = &get_library(&settings);
+    let config = &settings.to_config();
+
+    let issues = audit_library(config, &path_library, library, tolerance).unwrap();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&issues).unwrap());
+    } else {
+        for issue in &issues {
+            println!("{}", issue);
+        }
+    }
+    if !issues.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+fn duplicates(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    let path_library = get_path_library(&settings);
+
+    let library = get_library(&settings);
+    let path_lockfile = get_path_lockfile(&path_library);
+    let mut lockfile = read_lockfile(&path_lockfile).unwrap();
+    for (track_id, other_id, similarity) in find_duplicates(&mut lockfile, &library) {
+        println!("{} ~ {} ({:.0}% similar)", track_id, other_id, similarity * 100.0);
+    }
+    // Tracks removed from the library are gone from the lockfile now
+    write_lockfile(&path_lockfile, &lockfile).unwrap();
+}
+
+fn cache(matches: &ArgMatches) {
+    let settings = get_settings(matches);
+    let path_cache = match matches.get_one::<String>("cache").or(settings.cache.as_ref()) {
+        Some(path_cache) => path_cache.to_owned(),
+        None => {
+            eprintln!("ERR: The cache is turned off");
+            std::process::exit(1);
+        }
+    };
+
+    let mut cache = Cache::open(&path_cache, settings.cache_size).unwrap();
+    match matches.subcommand() {
+        Some(("list", _)) => {
+            let mut entries: Vec<_> = cache.entries().values().collect();
+            entries.sort_by_key(|entry| std::cmp::Reverse(entry.used));
+            for entry in entries {
+                println!("{:>8.1} MB {:>4} {}", entry.size as f64 / 1024.0 / 1024.0, entry.format, entry.url);
+            }
+            println!("{:.1} MB in {}", cache.size() as f64 / 1024.0 / 1024.0, path_cache);
+        }
+        Some(("verify", _)) => {
+            let broken = cache.verify().unwrap();
+            for (url, problem) in &broken {
+                println!("Removed {}: {}", url, problem);
+            }
+            println!("{} broken, {} ok", broken.len(), cache.entries().len());
+        }
+        Some(("clear", _)) => {
+            cache.clear().unwrap();
+            println!("Cleared {}", path_cache);
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn config(matches: &ArgMatches) {
+    let settings = get_settings(matches);
+    match matches.subcommand() {
+        Some(("show", _)) => print!("{}", serde_yaml::to_string(&settings).unwrap()),
+        _ => unreachable!(),
+    }
+}
+
+fn build(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+    let path_db = match settings.db {
+        Some(path_db) => path_db,
+        None => exit_with_error("No db directory given", &"Pass -i <db>, or set db in the config"),
+    };
+
+    let library = match scan_db(&path_db) {
+        Ok(library) => library,
+        Err(err) => exit_with_error(&format!("Could not read db {}", path_db), &err),
+    };
+    let result = match matches.get_one::<String>("file_out") {
+        Some(path_out) => std::fs::File::create(path_out)
+            .map_err(|err| err.to_string())
+            .and_then(|file| serde_yaml::to_writer(file, &library).map_err(|err| err.to_string())),
+        None => serde_yaml::to_writer(std::io::stdout(), &library).map_err(|err| err.to_string()),
+    };
+    if let Err(err) = result {
+        exit_with_error("Could not write library", &err);
+    }
+}
+
+fn import(matches: &ArgMatches) {
+    let fetcher = &HttpFetcher;
+    let (source, matches) = matches.subcommand().unwrap();
+
+    let get = |id: &str| matches.try_get_one::<String>(id).ok().flatten();
+
+    let dir_out = get("dir_out")
+        .map(String::as_str)
+        .unwrap_or(".");
+
+    let merge = get("merge");
+
+    let genre_default = &Genres(matches
+        .get_many::<String>("genre")
+        .unwrap_or_default()
+        .cloned()
+        .collect());
+
+    let result = match source {
+        "folder" => folder::import_folder(get("path").unwrap(), dir_out, genre_default),
+        "bandcamp" => {
+            let url = get("url").unwrap();
+            match bandcamp::is_discography_url(url) {
+                true => bandcamp::import_discography(fetcher, url, dir_out, genre_default),
+                false => bandcamp::get_album_from_url(fetcher, url)
+                    .and_then(|parsed| import_output(parsed, merge, genre_default)),
+            }
+        }
+        "soundcloud" => soundcloud_get(fetcher, get("url").unwrap())
+            .and_then(|parsed| import_output(parsed, merge, genre_default)),
+        _ => unreachable!(),
+    };
+    if let Err(err) = result {
+        exit_with_error("Could not import", &err);
+    }
+}
+
+/// Prints the album, or merges it into an existing album file and prints what changed.
+fn import_output(mut parsed: Album, merge: Option<&String>, genre_default: &Genres) -> Result<(), Error> {
+    set_default_genre(&mut parsed, genre_default);
+    if let Some(path) = merge {
+        let mut album = read_album(path)?;
+        let changes = merge_album(&mut album, parsed);
+        if changes.is_empty() {
+            println!("No changes");
+            return Ok(());
+        }
+        for change in changes {
+            println!("{}", change);
+        }
+        return write_album(path, &album);
+    }
+
+    let writer = std::io::stdout();
+    match serde_yaml::to_writer(writer, &parsed) {
+        Ok(_) => Ok(()),
+        Err(_) => Err(Error::new(ErrorKind::InvalidData, "Could not serialize library"))
+    }
+}
+
+fn find_artist(matches: &ArgMatches) {
+    let mut settings = get_settings(matches);
+    apply_library_args(&mut settings, matches);
+
+    let artist = matches
+        .get_one::<String>("artist")
+        .unwrap();
+
+    let library = &get_library(&settings);
+    match yar::find_artist(library, artist) {
+        Some(album) => println!("{}", album.artist),
+        None => {
+            eprintln!("ERR: Artist not found");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn completions(matches: &ArgMatches) {
+    let shell = *matches.get_one::<Shell>("shell").unwrap();
+    let command = &mut build_cli();
+    clap_complete::generate(shell, command, "yar", &mut std::io::stdout());
+}
+
+fn man(matches: &ArgMatches) {
+    let command = build_cli();
+    let result = match matches.get_one::<String>("dir_out") {
+        Some(dir_out) => std::fs::create_dir_all(dir_out)
+            .and_then(|_| clap_mangen::generate_to(command, dir_out)),
+        None => clap_mangen::Man::new(command).render(&mut std::io::stdout()),
+    };
+    if let Err(err) = result {
+        exit_with_error("Could not write man pages", &err);
+    }
 }
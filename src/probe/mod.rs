@@ -0,0 +1,60 @@
+// Inspects audio files with ffprobe and ffmpeg
+use std::io::{Error, ErrorKind};
+use std::process::Command;
+
+/// Returns the duration of an audio file in seconds.
+pub fn get_duration(path: &str) -> Result<f64, Error> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v", "error",
+            "-show_entries", "format=duration",
+            "-of", "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::new(ErrorKind::InvalidData, String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match stdout.trim().parse() {
+        Ok(duration) => Ok(duration),
+        Err(_) => Err(Error::new(ErrorKind::InvalidData, format!("Could not read the duration of {}", path))),
+    }
+}
+
+/// Decodes a whole file, returning the errors ffmpeg ran into.
+///
+/// Errors running ffmpeg itself are returned as `Err`.
+pub fn get_decode_errors(path: &str) -> Result<Option<String>, Error> {
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-i", path, "-map", "0:a", "-f", "null", "-"])
+        .output()?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_owned();
+    if output.status.success() && stderr.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(stderr))
+}
+
+/// Decodes the first audio stream of a file to mono 16 bit PCM.
+pub fn decode_samples(path: &str, sample_rate: u32) -> Result<Vec<i16>, Error> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-v", "error",
+            "-i", path,
+            "-map", "0:a:0",
+            "-ac", "1",
+            "-ar", &sample_rate.to_string(),
+            "-f", "s16le",
+            "-",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::other(String::from_utf8_lossy(&output.stderr).to_string()));
+    }
+    let samples = output.stdout
+        .chunks_exact(2)
+        .map(|bytes| i16::from_le_bytes([bytes[0], bytes[1]]))
+        .collect();
+    Ok(samples)
+}
@@ -0,0 +1,232 @@
+// Tagging through ffmpeg for containers the id3 crate can't handle. The audio
+// stream is copied, so this only rewrites the metadata.
+//
+// This isn't native tagging: every read runs ffprobe, and every write runs ffmpeg to copy
+// the whole file into a temporary one that replaces it. Tagging a large library in these
+// formats takes about as long as reading it once, and needs ffmpeg installed.
+use std::fs::{remove_file, rename, write};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::process::Command;
+
+/// Keys that are never carried over from the existing tags.
+const KEYS_SKIPPED: [&str; 1] = ["encoder"];
+
+const MP4_EXTENSIONS: [&str; 2] = ["m4a", "mp4"];
+
+/// What happens to the attached picture of a file.
+pub enum Picture<'a> {
+    Keep,
+    Remove,
+    Replace(&'a [u8]),
+}
+
+/// Rewrites the metadata of a file.
+///
+/// Existing tags that aren't overwritten are kept when `keep` returns true for their key.
+pub fn write_metadata(
+    path: &str,
+    metadata: &[(String, String)],
+    picture: Picture,
+    keep: &dyn Fn(&str) -> bool,
+) -> Result<(), Error> {
+    let mut merged: Vec<(String, String)> = read_metadata(path)?
+        .into_iter()
+        .filter(|(key, _)| !KEYS_SKIPPED.contains(&key.to_lowercase().as_str()))
+        .filter(|(key, _)| !metadata.iter().any(|(ours, _)| ours.eq_ignore_ascii_case(key)))
+        .filter(|(key, _)| keep(key))
+        .collect();
+    merged.extend(metadata.iter().cloned());
+
+    let path_tmp = get_path_tmp(path, &get_extension(path));
+    let path_metadata = get_path_tmp(path, "ffmeta");
+    let path_picture = get_path_tmp(path, "jpg");
+    write(&path_metadata, make_ffmetadata(&merged))?;
+
+    let mut args: Vec<String> = vec![
+        String::from("-y"),
+        String::from("-v"),
+        String::from("error"),
+        String::from("-i"),
+        String::from(path),
+        String::from("-f"),
+        String::from("ffmetadata"),
+        String::from("-i"),
+        path_metadata.clone(),
+    ];
+    if let Picture::Replace(picture) = picture {
+        write(&path_picture, picture)?;
+        args.push(String::from("-i"));
+        args.push(path_picture.clone());
+    }
+    args.extend([
+        String::from("-map"),
+        String::from("0:a"),
+        String::from("-map_metadata"),
+        String::from("1"),
+        String::from("-map_metadata:s:a"),
+        String::from("1"),
+        String::from("-c"),
+        String::from("copy"),
+    ]);
+    match picture {
+        Picture::Replace(_) => args.extend([
+            String::from("-map"),
+            String::from("2:v"),
+            String::from("-disposition:v:0"),
+            String::from("attached_pic"),
+            // The flac muxer picks the picture type from this
+            String::from("-metadata:s:v:0"),
+            String::from("comment=Cover (front)"),
+        ]),
+        Picture::Keep => args.extend([
+            String::from("-map"),
+            String::from("0:v?"),
+        ]),
+        Picture::Remove => {}
+    }
+    // The mp4 muxer only writes the tags it has an atom for, unless they're all written as keys
+    if MP4_EXTENSIONS.contains(&get_extension(path).to_lowercase().as_str()) {
+        args.extend([
+            String::from("-movflags"),
+            String::from("use_metadata_tags"),
+        ]);
+    }
+    args.push(path_tmp.clone());
+
+    let result = Command::new("ffmpeg").args(args).output();
+    let _ = remove_file(&path_metadata);
+    if matches!(picture, Picture::Replace(_)) {
+        let _ = remove_file(&path_picture);
+    }
+    let output = result?;
+    if !output.status.success() {
+        let _ = remove_file(&path_tmp);
+        eprintln!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(Error::new(ErrorKind::Unsupported, format!("ffmpeg could not tag {}", path)));
+    }
+    rename(&path_tmp, path)
+}
+
+/// Reads the container and audio stream tags of a file with ffprobe.
+pub fn read_metadata(path: &str) -> Result<Vec<(String, String)>, Error> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "quiet",
+            "-print_format",
+            "json",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "format_tags:stream_tags",
+            path,
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Could not read tags of {}", path)));
+    }
+    let probed: serde_json::Value = serde_json::from_slice(&output.stdout)?;
+
+    let mut metadata: Vec<(String, String)> = vec![];
+    let tags_format = probed["format"]["tags"].as_object().into_iter();
+    let tags_streams = probed["streams"].as_array().into_iter().flatten().filter_map(|stream| stream["tags"].as_object());
+    for tags in tags_format.chain(tags_streams) {
+        for (key, value) in tags {
+            let value = value.as_str().unwrap_or_default();
+            if !metadata.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(key)) {
+                metadata.push((key.to_owned(), value.to_owned()));
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Reads a single tag, ignoring the case of its key.
+pub fn read_metadata_key(path: &str, key: &str) -> Result<Option<String>, Error> {
+    let value = read_metadata(path)?
+        .into_iter()
+        .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value);
+    Ok(value)
+}
+
+/// Returns a lookup of the tags of a file that ignores the case of keys.
+pub fn get_value<'a>(metadata: &'a [(String, String)]) -> impl Fn(&str) -> Option<String> + 'a {
+    move |key: &str| {
+        metadata
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.to_owned())
+    }
+}
+
+/// Parses a track number that can include the total, eg. `3/12`.
+pub fn parse_track_number(value: &str) -> (u32, Option<u32>) {
+    let (number, total) = match value.split_once('/') {
+        Some((number, total)) => (number, total.trim().parse().ok()),
+        None => (value, None),
+    };
+    (number.trim().parse().unwrap_or_default(), total)
+}
+
+/// Parses the year of a date tag, eg. `2022-01-01`.
+pub fn parse_year(value: &str) -> i32 {
+    value.get(..4).and_then(|year| year.parse().ok()).unwrap_or_default()
+}
+
+/// Reads the attached picture of a file.
+pub fn read_picture(path: &str) -> Result<Vec<u8>, Error> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-v", "error",
+            "-i", path,
+            "-an",
+            "-map", "0:v:0?",
+            "-c:v", "copy",
+            "-f", "image2pipe",
+            "-",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(Error::new(ErrorKind::InvalidData, format!("Could not read picture of {}", path)));
+    }
+    Ok(output.stdout)
+}
+
+fn get_extension(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_owned()
+}
+
+/// Returns a path next to the file, eg. `01 - Name.tagging.flac`.
+fn get_path_tmp(path: &str, extension: &str) -> String {
+    Path::new(path)
+        .with_extension(format!("tagging.{}", extension))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// Creates an ffmpeg metadata file.
+///
+/// See: https://ffmpeg.org/ffmpeg-formats.html#Metadata-1
+fn make_ffmetadata(metadata: &[(String, String)]) -> String {
+    let escape = |text: &str| {
+        let mut escaped = String::new();
+        for c in text.chars() {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let mut result = String::from(";FFMETADATA1\n");
+    for (key, value) in metadata {
+        result.push_str(&format!("{}={}\n", escape(key), escape(value)));
+    }
+    result
+}
@@ -0,0 +1,181 @@
+use id3::{frame, Tag, TagLike};
+use std::io::{Error, ErrorKind};
+
+use super::{is_custom_key, make_provenance, parse_provenance, TagWriter, Tags, KEY_AT};
+
+/// Official audio source webpage, the page a track was downloaded from.
+const FRAME_SOURCE: &str = "WOAS";
+/// Official audio file webpage, players that don't show WOAS link to this one.
+const FRAME_FILE: &str = "WOAF";
+
+/// Writes ID3v2.4 tags, used for mp3 files.
+pub struct Id3Writer;
+
+impl TagWriter for Id3Writer {
+    fn write(&self, path: &str, tags: &Tags, strip: bool) -> Result<(), Error> {
+        let mut tag = match Tag::read_from_path(path) {
+            Ok(tag) => tag,
+            Err(_) => Tag::new(),
+        };
+        if strip {
+            let custom: Vec<frame::Frame> = tag
+                .frames()
+                .filter(|frame| {
+                    frame.id() == FRAME_SOURCE
+                        || frame.id() == FRAME_FILE
+                        || frame.content().extended_text().is_some_and(|text| is_custom_key(&text.description))
+                })
+                .cloned()
+                .collect();
+            tag = Tag::new();
+            for frame in custom {
+                tag.add_frame(frame);
+            }
+        }
+        // Frames are only replaced when their language and description match, so the
+        // ones yar owns are removed first
+        tag.remove("COMM");
+        tag.remove("USLT");
+        if !tags.cover.is_empty() {
+            tag.remove("APIC");
+        }
+
+        tag.set_album(&tags.album);
+        tag.set_title(&tags.title);
+        tag.set_album_artist(&tags.album_artist);
+        tag.set_track(tags.track);
+        tag.set_total_tracks(tags.track_total);
+        // ID3v2.4 stores multiple genres in one TCON frame, separated by null characters
+        if tags.genres.is_empty() {
+            tag.remove_genre();
+        } else {
+            tag.set_genre(tags.genres.join("\0"));
+        }
+        tag.set_year(tags.year);
+        tag.set_artist(&tags.artist);
+
+        if !tags.comment.is_empty() {
+            tag.add_frame(frame::Comment {
+                lang: String::from("EN"),
+                description: String::new(),
+                text: tags.comment.to_owned(),
+            });
+        }
+        if !tags.cover.is_empty() {
+            tag.add_frame(frame::Picture {
+                mime_type: "image/jpeg".to_string(),
+                picture_type: frame::PictureType::CoverFront,
+                description: "cover".to_string(),
+                data: tags.cover.clone(),
+            });
+        }
+        if let Some(lyrics) = &tags.lyrics {
+            tag.add_frame(frame::Lyrics {
+                lang: String::from("EN"),
+                description: String::new(),
+                text: String::from(lyrics),
+            });
+        };
+
+        if let Some(source) = &tags.source {
+            tag.remove_extended_text(Some(KEY_AT), None);
+            tag.add_frame(frame::Frame::link(FRAME_SOURCE, &source.url));
+            tag.add_frame(frame::Frame::link(FRAME_FILE, &source.url));
+        }
+        for (key, value) in make_provenance(tags) {
+            tag.add_frame(frame::ExtendedText {
+                description: key,
+                value,
+            });
+        }
+
+        write_tag(&tag, path)
+    }
+
+    fn read(&self, path: &str) -> Result<Tags, Error> {
+        let tag = match Tag::read_from_path(path) {
+            Ok(tag) => tag,
+            Err(err) => return Err(Error::new(ErrorKind::InvalidData, err.to_string())),
+        };
+        let text = |value: Option<&str>| value.unwrap_or_default().to_owned();
+        let custom: Vec<(String, String)> = tag
+            .extended_texts()
+            .map(|text| (text.description.to_owned(), text.value.to_owned()))
+            .collect();
+        let (track_id, source) = parse_provenance(&custom);
+        let cover = tag
+            .pictures()
+            .find(|picture| picture.picture_type == frame::PictureType::CoverFront)
+            .or_else(|| tag.pictures().next())
+            .map(|picture| picture.data.clone())
+            .unwrap_or_default();
+        let lyrics = tag.lyrics().next().map(|lyrics| lyrics.text.to_owned());
+
+        Ok(Tags {
+            title: text(tag.title()),
+            album: text(tag.album()),
+            album_artist: text(tag.album_artist()),
+            artist: text(tag.artist()),
+            track: tag.track().unwrap_or_default(),
+            track_total: tag.total_tracks().unwrap_or_default(),
+            genres: tag.genres().unwrap_or_default().into_iter().map(String::from).collect(),
+            year: tag.year().or_else(|| tag.date_recorded().map(|date| date.year)).unwrap_or_default(),
+            comment: tag.comments().map(|comment| comment.text.to_owned()).collect::<Vec<String>>().join("\n"),
+            lyrics,
+            cover,
+            track_id,
+            source,
+        })
+    }
+
+    fn write_custom(&self, path: &str, values: &[(String, String)]) -> Result<(), Error> {
+        let mut tag = match Tag::read_from_path(path) {
+            Ok(tag) => tag,
+            Err(_) => Tag::new(),
+        };
+        for (key, value) in values {
+            tag.add_frame(frame::ExtendedText {
+                description: key.to_owned(),
+                value: value.to_owned(),
+            });
+        }
+        write_tag(&tag, path)
+    }
+
+    fn read_custom(&self, path: &str, key: &str) -> Result<Option<String>, Error> {
+        let tag = match Tag::read_from_path(path) {
+            Ok(tag) => tag,
+            Err(id3::Error { kind: id3::ErrorKind::NoTag, .. }) => return Ok(None),
+            Err(err) => return Err(Error::new(ErrorKind::InvalidData, err.to_string())),
+        };
+        let value = tag
+            .extended_texts()
+            .find(|text| text.description.eq_ignore_ascii_case(key))
+            .map(|text| text.value.to_owned());
+        Ok(value)
+    }
+
+    fn read_all_custom(&self, path: &str) -> Result<Vec<(String, String)>, Error> {
+        let tag = match Tag::read_from_path(path) {
+            Ok(tag) => tag,
+            Err(id3::Error { kind: id3::ErrorKind::NoTag, .. }) => return Ok(vec![]),
+            Err(err) => return Err(Error::new(ErrorKind::InvalidData, err.to_string())),
+        };
+        let values = tag
+            .extended_texts()
+            .filter(|text| is_custom_key(&text.description))
+            .map(|text| (text.description.to_owned(), text.value.to_owned()))
+            .collect();
+        Ok(values)
+    }
+}
+
+fn write_tag(tag: &Tag, path: &str) -> Result<(), Error> {
+    match tag.write_to_path(path, id3::Version::Id3v24) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            println!("{}", err);
+            Err(Error::new(ErrorKind::Unsupported, "tagging failed"))
+        }
+    }
+}
@@ -1,9 +1,101 @@
+mod ffmpeg;
+mod id3v2;
+mod mp4;
+mod vorbis;
+
 use chrono::Datelike;
-use id3::{frame, Tag, TagLike};
 use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::{Album, Genres, Location, Track, Sample, get_track_id, get_track_title};
+
+/// Everything yar writes into a track's tags, independent of the file format.
+pub struct Tags {
+    pub title: String,
+    pub album: String,
+    pub album_artist: String,
+    pub artist: String,
+    pub track: u32,
+    pub track_total: u32,
+    pub genres: Vec<String>,
+    pub year: i32,
+    pub comment: String,
+    pub lyrics: Option<String>,
+    pub cover: Vec<u8>,
+    /// See [`get_track_id`].
+    pub track_id: String,
+    /// Where the file was downloaded from, `None` keeps the source that was tagged before.
+    pub source: Option<Location>,
+}
+
+/// Prefixes of the free form tags yar writes, which are kept when other tags are stripped.
+const CUSTOM_KEY_PREFIXES: [&str; 2] = ["REPLAYGAIN_", "YAR_"];
+
+pub const KEY_TRACK_ID: &str = "YAR_TRACK_ID";
+pub const KEY_VERSION: &str = "YAR_VERSION";
+pub const KEY_SOURCE: &str = "YAR_SOURCE";
+pub const KEY_AT: &str = "YAR_AT";
 
-use crate::{Album, Track, Sample, get_track_title};
+impl Tags {
+    /// Lists the fields that differ from the expected tags, ignoring the cover and provenance.
+    pub fn diff(&self, expected: &Tags) -> Vec<String> {
+        let mut diff = vec![];
+        let mut compare = |field: &str, actual: String, expected: String| {
+            if actual != expected {
+                diff.push(format!("{} is {:?}, expected {:?}", field, actual, expected));
+            }
+        };
+        compare("title", self.title.to_owned(), expected.title.to_owned());
+        compare("album", self.album.to_owned(), expected.album.to_owned());
+        compare("album artist", self.album_artist.to_owned(), expected.album_artist.to_owned());
+        compare("artist", self.artist.to_owned(), expected.artist.to_owned());
+        compare("track", self.track.to_string(), expected.track.to_string());
+        compare("track total", self.track_total.to_string(), expected.track_total.to_string());
+        compare("genre", self.genres.join(", "), expected.genres.join(", "));
+        compare("year", self.year.to_string(), expected.year.to_string());
+        compare("comment", self.comment.to_owned(), expected.comment.to_owned());
+        compare("lyrics", self.lyrics.clone().unwrap_or_default(), expected.lyrics.clone().unwrap_or_default());
+        diff
+    }
+}
+
+/// Writes tags in the container specific way of an audio format.
+///
+/// Writing the same tags twice gives the same file. With `strip`, every existing tag
+/// is removed except the free form ones yar wrote, eg. tags from the downloader.
+pub trait TagWriter {
+    fn write(&self, path: &str, tags: &Tags, strip: bool) -> Result<(), Error>;
 
+    /// Reads the tags of a file, eg. to import files that were tagged by hand.
+    fn read(&self, path: &str) -> Result<Tags, Error>;
+
+    /// Writes free form tags, eg. `REPLAYGAIN_TRACK_GAIN`.
+    fn write_custom(&self, path: &str, values: &[(String, String)]) -> Result<(), Error>;
+
+    /// Reads a free form tag.
+    fn read_custom(&self, path: &str, key: &str) -> Result<Option<String>, Error>;
+
+    /// Reads every free form tag yar wrote, see [`is_custom_key`].
+    fn read_all_custom(&self, path: &str) -> Result<Vec<(String, String)>, Error>;
+}
+
+/// Returns the tag writer for a file based on its extension.
+pub fn get_tag_writer(path: &str) -> Result<Box<dyn TagWriter>, Error> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "mp3" => Ok(Box::new(id3v2::Id3Writer)),
+        "flac" | "ogg" | "opus" => Ok(Box::new(vorbis::VorbisWriter)),
+        "m4a" => Ok(Box::new(mp4::Mp4Writer)),
+        _ => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!("Can not tag files with extension: {}", extension),
+        )),
+    }
+}
 
 pub fn tag_track(
     path_out: &str,
@@ -11,71 +103,113 @@ pub fn tag_track(
     track: &Track,
     track_pos_str: &str,
     cover: Vec<u8>,
+    source: Option<&Location>,
+    strip: bool,
 ) -> Result<(), Error> {
-    let track_name = get_track_title(track);
-    let album_name = &album.name;
-    let album_artist = &album.artist;
-    let album_track_count: u32 = album.track_count.try_into().unwrap();
-    let track_pos: u32 = track_pos_str.parse().unwrap();
-    let genre = &album.genre;
-    let year = album.released.year();
-    let lyrics = &track.lyrics;
-    let comment = make_comment(track);
-    let artist = make_artist(album, track);
-
-    let mut tag = match Tag::read_from_path(path_out) {
-        Ok(tag) => tag,
-        Err(_) => Tag::new(),
-    };
+    let tags = make_tags(album, track, track_pos_str, cover, source);
+    get_tag_writer(path_out)?.write(path_out, &tags, strip)
+}
 
-    tag.set_album(album_name);
-    tag.set_title(track_name);
-    tag.set_album_artist(album_artist);
-    tag.set_track(track_pos);
-    tag.set_total_tracks(album_track_count);
-    tag.set_genre(genre);
-    tag.set_year(year);
-    tag.set_artist(artist);
-
-    if !comment.is_empty() {
-        tag.add_frame(frame::Comment {
-            lang: String::from("EN"),
-            description: String::new(),
-            text: comment,
-        });
-    }
-    if !cover.is_empty() {
-        tag.add_frame(frame::Picture {
-            mime_type: "image/jpeg".to_string(),
-            picture_type: frame::PictureType::Other,
-            description: "cover".to_string(),
-            data: cover,
-        });
-    }
-    match lyrics {
-        Some(lyrics) => {
-            tag.add_frame(frame::Lyrics {
-                lang: String::from("EN"),
-                description: String::new(),
-                text: String::from(lyrics),
-            });
-        }
-        None => (),
+/// Returns the tags yar writes for a track, without its cover.
+pub fn get_expected_tags(album: &Album, track: &Track, track_pos_str: &str) -> Tags {
+    make_tags(album, track, track_pos_str, vec![], None)
+}
+
+/// Reads the tags of a file of any supported format.
+pub fn read_tags(path: &str) -> Result<Tags, Error> {
+    get_tag_writer(path)?.read(path)
+}
+
+/// Writes free form tags to a file of any supported format.
+pub fn write_custom_tags(path: &str, values: &[(String, String)]) -> Result<(), Error> {
+    get_tag_writer(path)?.write_custom(path, values)
+}
+
+/// Reads a free form tag from a file of any supported format.
+pub fn read_custom_tag(path: &str, key: &str) -> Result<Option<String>, Error> {
+    get_tag_writer(path)?.read_custom(path, key)
+}
+
+/// Reads the free form tags of a file other than where it came from, eg. ReplayGain.
+pub fn read_custom_tags(path: &str) -> Result<Vec<(String, String)>, Error> {
+    let values = get_tag_writer(path)?.read_all_custom(path)?;
+    let provenance = [KEY_TRACK_ID, KEY_VERSION, KEY_SOURCE, KEY_AT];
+    Ok(values
+        .into_iter()
+        .filter(|(key, _)| !provenance.iter().any(|ours| ours.eq_ignore_ascii_case(key)))
+        .collect())
+}
+
+/// Reads the location a file was downloaded from.
+pub fn read_source(path: &str) -> Option<Location> {
+    let url = read_custom_tag(path, KEY_SOURCE).ok()??;
+    let at = read_custom_tag(path, KEY_AT).ok().flatten();
+    Some(Location { url, at })
+}
+
+/// Parses the free form tags that tell where a file came from.
+fn parse_provenance(values: &[(String, String)]) -> (String, Option<Location>) {
+    let get = |key: &str| {
+        values
+            .iter()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.to_owned())
     };
+    let source = get(KEY_SOURCE).map(|url| Location { url, at: get(KEY_AT) });
+    (get(KEY_TRACK_ID).unwrap_or_default(), source)
+}
 
-    match tag.write_to_path(path_out, id3::Version::Id3v24) {
-        Ok(_) => Ok(()),
-        Err(err) => {
-            println!("{}", err);
-            Err(Error::new(ErrorKind::Unsupported, "tagging failed"))
+/// Returns the free form tags that tell where a file came from.
+fn make_provenance(tags: &Tags) -> Vec<(String, String)> {
+    let mut provenance = vec![
+        (String::from(KEY_TRACK_ID), tags.track_id.to_owned()),
+        (String::from(KEY_VERSION), String::from(env!("CARGO_PKG_VERSION"))),
+    ];
+    if let Some(source) = &tags.source {
+        provenance.push((String::from(KEY_SOURCE), source.url.to_owned()));
+        if let Some(at) = &source.at {
+            provenance.push((String::from(KEY_AT), at.to_owned()));
         }
     }
+    provenance
+}
+
+/// Splits the genres yar joins with `;` for formats without multi value tags.
+fn split_genres(genres: &str) -> Vec<String> {
+    genres
+        .split(';')
+        .map(str::trim)
+        .filter(|genre| !genre.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Checks if a free form tag was written by yar.
+fn is_custom_key(key: &str) -> bool {
+    let key = key.to_uppercase();
+    CUSTOM_KEY_PREFIXES.iter().any(|prefix| key.starts_with(prefix))
+}
+
+fn make_tags(album: &Album, track: &Track, track_pos_str: &str, cover: Vec<u8>, source: Option<&Location>) -> Tags {
+    Tags {
+        title: get_track_title(track),
+        album: album.name.to_owned(),
+        album_artist: album.artist.to_owned(),
+        artist: make_artist(album, track),
+        track: track_pos_str.parse().unwrap(),
+        track_total: album.track_count.try_into().unwrap(),
+        genres: make_genres(album, track).0.clone(),
+        year: album.released.year(),
+        comment: make_comment(track),
+        lyrics: track.lyrics.clone(),
+        cover,
+        track_id: get_track_id(album, track_pos_str),
+        source: source.cloned(),
+    }
 }
 
 fn make_comment_from_sample(sample: &Sample) -> String {
-    let sample_type = match sample.r#type {
-        _ => "SAMPLE",
-    };
+    let sample_type = "SAMPLE";
     format!("{}: {}, {}", sample_type, sample.artist, sample.name)
 }
 
@@ -102,3 +236,7 @@ fn make_artist(album: &Album, track: &Track) -> String {
         None => album.artist.to_string(),
     }
 }
+
+fn make_genres<'a>(album: &'a Album, track: &'a Track) -> &'a Genres {
+    track.genre.as_ref().unwrap_or(&album.genre)
+}
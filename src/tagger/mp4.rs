@@ -0,0 +1,103 @@
+use std::io::Error;
+
+use super::ffmpeg::{
+    get_value, parse_track_number, parse_year, read_metadata, read_metadata_key, read_picture, write_metadata, Picture,
+};
+use super::{is_custom_key, make_provenance, parse_provenance, split_genres, TagWriter, Tags, KEY_AT};
+
+/// Atoms that are written by yar, as ffprobe names them.
+const KEYS_OWNED: [&str; 9] = [
+    "title",
+    "album",
+    "album_artist",
+    "artist",
+    "track",
+    "date",
+    "genre",
+    "comment",
+    "lyrics",
+];
+
+/// Writes MP4 metadata through ffmpeg, used for m4a files.
+pub struct Mp4Writer;
+
+impl TagWriter for Mp4Writer {
+    fn write(&self, path: &str, tags: &Tags, strip: bool) -> Result<(), Error> {
+        let mut metadata = vec![
+            (String::from("title"), tags.title.to_owned()),
+            (String::from("album"), tags.album.to_owned()),
+            (String::from("album_artist"), tags.album_artist.to_owned()),
+            (String::from("artist"), tags.artist.to_owned()),
+            (String::from("track"), format!("{}/{}", tags.track, tags.track_total)),
+            (String::from("date"), tags.year.to_string()),
+        ];
+        if !tags.genres.is_empty() {
+            metadata.push((String::from("genre"), tags.genres.join(";")));
+        }
+        if !tags.comment.is_empty() {
+            metadata.push((String::from("comment"), tags.comment.to_owned()));
+        }
+        if let Some(lyrics) = &tags.lyrics {
+            metadata.push((String::from("lyrics"), lyrics.to_owned()));
+        }
+        metadata.extend(make_provenance(tags));
+
+        // Tags yar owns are dropped even when the library doesn't set them anymore
+        let keep = |key: &str| {
+            // A new source replaces the offset of the old one, even if it has none
+            if key.eq_ignore_ascii_case(KEY_AT) && tags.source.is_some() {
+                return false;
+            }
+            if strip {
+                return is_custom_key(key);
+            }
+            !KEYS_OWNED.contains(&key.to_lowercase().as_str())
+        };
+        let picture = match (tags.cover.is_empty(), strip) {
+            (false, _) => Picture::Replace(&tags.cover),
+            (true, false) => Picture::Keep,
+            (true, true) => Picture::Remove,
+        };
+        write_metadata(path, &metadata, picture, &keep)
+    }
+
+    fn read(&self, path: &str) -> Result<Tags, Error> {
+        let metadata = read_metadata(path)?;
+        let get = get_value(&metadata);
+        let (track, track_total) = parse_track_number(&get("track").unwrap_or_default());
+        let (track_id, source) = parse_provenance(&metadata);
+
+        Ok(Tags {
+            title: get("title").unwrap_or_default(),
+            album: get("album").unwrap_or_default(),
+            album_artist: get("album_artist").unwrap_or_default(),
+            artist: get("artist").unwrap_or_default(),
+            track,
+            track_total: track_total.unwrap_or_default(),
+            genres: get("genre").map(|genres| split_genres(&genres)).unwrap_or_default(),
+            year: parse_year(&get("date").unwrap_or_default()),
+            comment: get("comment").unwrap_or_default(),
+            lyrics: get("lyrics"),
+            cover: read_picture(path).unwrap_or_default(),
+            track_id,
+            source,
+        })
+    }
+
+    // Free form tags are written as metadata keys, see `write_metadata`
+    fn write_custom(&self, path: &str, values: &[(String, String)]) -> Result<(), Error> {
+        write_metadata(path, values, Picture::Keep, &|_| true)
+    }
+
+    fn read_custom(&self, path: &str, key: &str) -> Result<Option<String>, Error> {
+        read_metadata_key(path, key)
+    }
+
+    fn read_all_custom(&self, path: &str) -> Result<Vec<(String, String)>, Error> {
+        let values = read_metadata(path)?
+            .into_iter()
+            .filter(|(key, _)| is_custom_key(key))
+            .collect();
+        Ok(values)
+    }
+}
@@ -0,0 +1,161 @@
+use std::io::Error;
+use std::path::Path;
+
+use super::ffmpeg::{
+    get_value, parse_track_number, parse_year, read_metadata, read_metadata_key, read_picture, write_metadata, Picture,
+};
+use super::{is_custom_key, make_provenance, parse_provenance, split_genres, TagWriter, Tags, KEY_AT};
+
+/// Picture type of the embedded cover (front cover), see the ID3v2 APIC frame.
+const PICTURE_TYPE: u32 = 3;
+
+const KEY_PICTURE: &str = "METADATA_BLOCK_PICTURE";
+
+/// Comments that are written by yar.
+const KEYS_OWNED: [&str; 11] = [
+    "TITLE",
+    "ALBUM",
+    "ALBUMARTIST",
+    "ARTIST",
+    "TRACKNUMBER",
+    "TRACKTOTAL",
+    "DATE",
+    "GENRE",
+    "COMMENT",
+    "LYRICS",
+    KEY_PICTURE,
+];
+
+/// Writes Vorbis comments through ffmpeg, used for flac, ogg and opus files.
+pub struct VorbisWriter;
+
+impl TagWriter for VorbisWriter {
+    fn write(&self, path: &str, tags: &Tags, strip: bool) -> Result<(), Error> {
+        let mut metadata = vec![
+            (String::from("TITLE"), tags.title.to_owned()),
+            (String::from("ALBUM"), tags.album.to_owned()),
+            (String::from("ALBUMARTIST"), tags.album_artist.to_owned()),
+            (String::from("ARTIST"), tags.artist.to_owned()),
+            (String::from("TRACKNUMBER"), tags.track.to_string()),
+            (String::from("TRACKTOTAL"), tags.track_total.to_string()),
+            (String::from("DATE"), tags.year.to_string()),
+        ];
+        if !tags.genres.is_empty() {
+            metadata.push((String::from("GENRE"), tags.genres.join(";")));
+        }
+        if !tags.comment.is_empty() {
+            metadata.push((String::from("COMMENT"), tags.comment.to_owned()));
+        }
+        if let Some(lyrics) = &tags.lyrics {
+            metadata.push((String::from("LYRICS"), lyrics.to_owned()));
+        }
+        metadata.extend(make_provenance(tags));
+
+        // Tags yar owns are dropped even when the library doesn't set them anymore
+        let keep_cover = tags.cover.is_empty() && !strip;
+        let keep = |key: &str| {
+            let key = key.to_uppercase();
+            // A new source replaces the offset of the old one, even if it has none
+            if key == KEY_AT && tags.source.is_some() {
+                return false;
+            }
+            if strip {
+                return is_custom_key(&key);
+            }
+            !KEYS_OWNED.contains(&key.as_str()) || (key == KEY_PICTURE && keep_cover)
+        };
+
+        // flac has its own picture block which ffmpeg writes from an attached picture,
+        // ogg containers only support the base64 encoded comment
+        let is_flac = Path::new(path).extension().is_some_and(|ext| ext == "flac");
+        if tags.cover.is_empty() || is_flac {
+            let picture = match (tags.cover.is_empty(), keep_cover) {
+                (false, _) => Picture::Replace(&tags.cover),
+                (true, true) => Picture::Keep,
+                (true, false) => Picture::Remove,
+            };
+            return write_metadata(path, &metadata, picture, &keep);
+        }
+        let picture = make_picture_block(&tags.cover, "image/jpeg");
+        metadata.push((String::from(KEY_PICTURE), base64::encode(picture)));
+        write_metadata(path, &metadata, Picture::Remove, &keep)
+    }
+
+    fn read(&self, path: &str) -> Result<Tags, Error> {
+        let metadata = read_metadata(path)?;
+        let get = get_value(&metadata);
+        let (track, track_total) = parse_track_number(&get("TRACKNUMBER").unwrap_or_default());
+        let (track_id, source) = parse_provenance(&metadata);
+        let cover = match get(KEY_PICTURE).and_then(|block| base64::decode(block.trim()).ok()) {
+            Some(block) => parse_picture_block(&block).unwrap_or_default(),
+            None => read_picture(path).unwrap_or_default(),
+        };
+
+        Ok(Tags {
+            title: get("TITLE").unwrap_or_default(),
+            album: get("ALBUM").unwrap_or_default(),
+            album_artist: get("ALBUMARTIST").or_else(|| get("album_artist")).unwrap_or_default(),
+            artist: get("ARTIST").unwrap_or_default(),
+            track,
+            track_total: get("TRACKTOTAL")
+                .and_then(|total| total.parse().ok())
+                .or(track_total)
+                .unwrap_or_default(),
+            genres: get("GENRE").map(|genres| split_genres(&genres)).unwrap_or_default(),
+            year: parse_year(&get("DATE").unwrap_or_default()),
+            comment: get("COMMENT").unwrap_or_default(),
+            lyrics: get("LYRICS"),
+            cover,
+            track_id,
+            source,
+        })
+    }
+
+    fn write_custom(&self, path: &str, values: &[(String, String)]) -> Result<(), Error> {
+        write_metadata(path, values, Picture::Keep, &|_| true)
+    }
+
+    fn read_custom(&self, path: &str, key: &str) -> Result<Option<String>, Error> {
+        read_metadata_key(path, key)
+    }
+
+    fn read_all_custom(&self, path: &str) -> Result<Vec<(String, String)>, Error> {
+        let values = read_metadata(path)?
+            .into_iter()
+            .filter(|(key, _)| is_custom_key(key))
+            .collect();
+        Ok(values)
+    }
+}
+
+/// Reads the picture data of a FLAC picture block.
+fn parse_picture_block(block: &[u8]) -> Option<Vec<u8>> {
+    let read_u32 = |offset: usize| -> Option<usize> {
+        let bytes = block.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes(bytes.try_into().ok()?) as usize)
+    };
+    let mut offset = 4;
+    offset += 4 + read_u32(offset)?;
+    offset += 4 + read_u32(offset)?;
+    offset += 16;
+    let length = read_u32(offset)?;
+    block.get(offset + 4..offset + 4 + length).map(|data| data.to_vec())
+}
+
+/// Creates a FLAC picture block.
+///
+/// See: https://xiph.org/flac/format.html#metadata_block_picture
+fn make_picture_block(data: &[u8], mime_type: &str) -> Vec<u8> {
+    let description = "cover";
+    let mut block = vec![];
+    block.extend(PICTURE_TYPE.to_be_bytes());
+    block.extend((mime_type.len() as u32).to_be_bytes());
+    block.extend(mime_type.as_bytes());
+    block.extend((description.len() as u32).to_be_bytes());
+    block.extend(description.as_bytes());
+    // Width, height, color depth and palette size are optional
+    block.extend([0u8; 16]);
+    block.extend((data.len() as u32).to_be_bytes());
+    block.extend(data);
+    block
+}
@@ -0,0 +1,214 @@
+// Creates a copy of a library with a different codec, eg. for phones
+use std::collections::HashMap;
+use std::fs::{copy, create_dir_all, metadata, read, remove_file};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_path_config_dir;
+use crate::cover::{find_cover, make_embedded_cover};
+use crate::tagger::{read_custom_tags, read_source, tag_track, write_custom_tags};
+use crate::{get_path_album, get_path_track, get_stdout, Album, Config, Library, Location, Track, AUDIO_FORMATS};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Profiles {
+    pub profiles: HashMap<String, Profile>,
+}
+
+/// How the tracks of a transcoded library are encoded.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Profile {
+    pub format: String,
+    pub codec: Option<String>,
+    pub bitrate: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u8>,
+}
+
+/// Returns the default path of the profiles file, `~/.config/yar/profiles.yml`.
+pub fn get_path_profiles() -> String {
+    format!("{}/profiles.yml", get_path_config_dir())
+}
+
+/// Returns a profiles struct from a file path.
+///
+/// A profile with a format yar can't tag is an error, so it fails before anything is transcoded.
+pub fn read_profiles(path: &str) -> Result<Profiles, Error> {
+    let file = std::fs::File::open(path)?;
+    let profiles: Profiles = match serde_yaml::from_reader(file) {
+        Ok(profiles) => profiles,
+        Err(err) => return Err(Error::new(ErrorKind::InvalidData, format!("Could not read profiles {}: {}", path, err))),
+    };
+    for (name, profile) in profiles.profiles.iter() {
+        if !AUDIO_FORMATS.contains(&profile.format.as_str()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Profile {} has format {}, which is not one of {}", name, profile.format, AUDIO_FORMATS.join(", ")),
+            ));
+        }
+    }
+    Ok(profiles)
+}
+
+/// Transcodes every track of a library into a second library directory.
+///
+/// Tracks are only transcoded when the source file is newer than the transcoded one. A track
+/// that can't be transcoded or tagged is skipped.
+pub fn transcode_library(
+    config: &Config,
+    path_library: &str,
+    path_out: &str,
+    library: &Library,
+    profile: &Profile,
+) -> Result<(), Error> {
+    for album in &library.albums {
+        transcode_album(config, path_library, path_out, album, profile)?;
+    }
+    Ok(())
+}
+
+fn transcode_album(
+    config: &Config,
+    path_library: &str,
+    path_out: &str,
+    album: &Album,
+    profile: &Profile,
+) -> Result<(), Error> {
+    let path_album = get_path_album(path_library, album);
+    let path_album_out = get_path_album(path_out, album);
+    if !Path::new(&path_album).exists() {
+        println!("Skipping missing album: {}", path_album);
+        return Ok(());
+    }
+
+    println!("{}", &path_album_out);
+    create_dir_all(&path_album_out)?;
+
+    let mut cover = vec![];
+    if let Some(path_cover) = find_cover(&path_album) {
+        let path_cover_out = path_cover.replacen(&path_album, &path_album_out, 1);
+        if needs_update(config, &path_cover, &path_cover_out) {
+            copy(&path_cover, &path_cover_out)?;
+        }
+        cover = match make_embedded_cover(&read(&path_cover)?, config.cover_size) {
+            Ok(cover) => cover,
+            Err(err) => {
+                println!("ERR: Could not read the cover of {}", path_album);
+                println!("{}", err);
+                vec![]
+            }
+        };
+    }
+
+    for (track_position, track) in album.tracks.iter() {
+        let path_track = get_path_track(&path_album, track_position, track, config.audio_fmt);
+        let path_track_out = get_path_track(&path_album_out, track_position, track, &profile.format);
+        if !Path::new(&path_track).exists() {
+            println!("Skipping missing track: {}", path_track);
+            continue;
+        }
+        if !needs_update(config, &path_track, &path_track_out) {
+            if config.debug {
+                println!("Skipping: {}", path_track_out)
+            }
+            continue;
+        }
+
+        println!("{}: {}", track_position, track.name);
+        if let Err(err) = transcode_track(config, &path_track, &path_track_out, profile) {
+            println!("ERR: Could not transcode {}", path_track);
+            println!("{}", err);
+            continue;
+        }
+        let source = read_source(&path_track);
+        if let Err(err) = retag_track(&path_track_out, album, track, track_position, cover.clone(), source.as_ref()) {
+            println!("ERR: Could not tag {}", path_track_out);
+            println!("{}", err);
+            // Otherwise the untagged file would be up to date on the next run
+            let _ = remove_file(&path_track_out);
+            continue;
+        }
+        carry_custom_tags(&path_track, &path_track_out);
+    }
+    Ok(())
+}
+
+/// Checks if the output is missing or older than its source.
+fn needs_update(config: &Config, path_source: &str, path_out: &str) -> bool {
+    if config.force {
+        return true;
+    }
+    let modified = |path: &str| metadata(path).and_then(|meta| meta.modified());
+    match (modified(path_source), modified(path_out)) {
+        (Ok(source), Ok(out)) => source > out,
+        _ => true,
+    }
+}
+
+fn transcode_track(config: &Config, path_source: &str, path_out: &str, profile: &Profile) -> Result<(), Error> {
+    let args = get_ffmpeg_args(path_source, path_out, profile);
+    let stdout = get_stdout(config.debug_ffmpeg);
+    let output = Command::new("ffmpeg").args(args).stdout(stdout).output()?;
+    if !output.status.success() {
+        return Err(Error::other(String::from_utf8_lossy(&output.stderr)));
+    }
+    Ok(())
+}
+
+// Tags are written from the library rather than copied, so they are the same in every format.
+// Free form tags the library doesn't have are copied afterwards, see `carry_custom_tags`
+fn retag_track(
+    path: &str,
+    album: &Album,
+    track: &Track,
+    track_position: &str,
+    cover: Vec<u8>,
+    source: Option<&Location>,
+) -> Result<(), Error> {
+    // The transcoded file has no tags to strip, ffmpeg was told not to copy them
+    tag_track(path, album, track, track_position, cover, source, false)
+}
+
+/// Copies the free form tags that aren't in the library, eg. ReplayGain, to the transcoded file.
+fn carry_custom_tags(path_source: &str, path_out: &str) {
+    let result = read_custom_tags(path_source).and_then(|values| match values.is_empty() {
+        true => Ok(()),
+        false => write_custom_tags(path_out, &values),
+    });
+    if let Err(err) = result {
+        println!("ERR: Could not copy the custom tags of {}", path_source);
+        println!("{}", err);
+    }
+}
+
+fn get_ffmpeg_args(path_source: &str, path_out: &str, profile: &Profile) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        String::from("-y"),
+        String::from("-i"),
+        String::from(path_source),
+        String::from("-map"),
+        String::from("0:a"),
+        String::from("-map_metadata"),
+        String::from("-1"),
+    ];
+    if let Some(codec) = &profile.codec {
+        args.push(String::from("-c:a"));
+        args.push(codec.to_owned());
+    }
+    if let Some(bitrate) = &profile.bitrate {
+        args.push(String::from("-b:a"));
+        args.push(bitrate.to_owned());
+    }
+    if let Some(sample_rate) = profile.sample_rate {
+        args.push(String::from("-ar"));
+        args.push(sample_rate.to_string());
+    }
+    if let Some(channels) = profile.channels {
+        args.push(String::from("-ac"));
+        args.push(channels.to_string());
+    }
+    args.push(String::from(path_out));
+    args
+}
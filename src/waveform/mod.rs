@@ -0,0 +1,134 @@
+// Waveforms computed from downloaded audio, in the same format as soundcloud's
+pub mod render;
+
+use std::fs::write;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+use crate::importer::{read_album, write_album};
+use crate::probe::decode_samples;
+use crate::{get_path_album, get_path_track, Config, Library, Wave};
+use render::{render_png, render_svg, RenderOptions};
+
+/// Number of points soundcloud uses for a waveform.
+pub const WAVE_POINTS: usize = 1800;
+
+/// Sample rate the audio is decoded at, high enough to catch the peaks.
+const SAMPLE_RATE: u32 = 8000;
+
+/// Computes a peak waveform of an audio file.
+///
+/// Each point is the loudest sample of its part of the track, scaled from 0 to 255.
+pub fn generate_wave(path: &str, points: usize) -> Result<Wave, Error> {
+    let samples = decode_samples(path, SAMPLE_RATE)?;
+    if samples.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidData, format!("No audio in {}", path)));
+    }
+    Ok(make_wave(&samples, points))
+}
+
+/// Reduces samples to peaks.
+///
+/// # Example
+/// ```
+/// let wave = yar::waveform::make_wave(&[0, 16384, -32767, 100], 2);
+/// assert_eq!(wave.length, 2);
+/// assert_eq!(wave.points, vec![127, 255]);
+/// assert_eq!(yar::waveform::make_wave(&[1; 1801], 1800).points.len(), 1800);
+/// ```
+pub fn make_wave(samples: &[i16], points: usize) -> Wave {
+    let points = points.clamp(1, samples.len().max(1));
+    // Buckets differ in size by at most one sample, so there are always `points` of them
+    let points: Vec<u8> = (0..points)
+        .map(|idx| &samples[idx * samples.len() / points..(idx + 1) * samples.len() / points])
+        .map(|bucket| {
+            let peak = bucket.iter().map(|sample| sample.unsigned_abs()).max().unwrap_or(0);
+            let peak = peak.min(i16::MAX as u16);
+            ((peak as f32 / i16::MAX as f32) * u8::MAX as f32) as u8
+        })
+        .collect();
+    Wave {
+        length: points.len().try_into().unwrap(),
+        points,
+    }
+}
+
+/// Fills in the waves of an album file from its downloaded tracks.
+///
+/// Tracks that already have a wave are skipped unless forced.
+pub fn generate_album_waves(config: &Config, path_file: &str, path_library: &str, points: usize) -> Result<(), Error> {
+    let mut album = read_album(path_file)?;
+    let path_album = get_path_album(path_library, &album);
+    let mut changed = false;
+
+    for (track_position, track) in album.tracks.iter_mut() {
+        if track.wave.is_some() && !config.force {
+            if config.debug {
+                println!("Skipping: {}: {}", track_position, track.name)
+            }
+            continue;
+        }
+        let path_track = get_path_track(&path_album, track_position, track, config.audio_fmt);
+        if !Path::new(&path_track).exists() {
+            println!("Skipping missing track: {}", path_track);
+            continue;
+        }
+
+        println!("{}: {}", track_position, track.name);
+        match generate_wave(&path_track, points) {
+            Ok(wave) => {
+                track.wave = Some(wave);
+                changed = true;
+            }
+            Err(err) => {
+                println!("ERR: Could not generate wave for {}", path_track);
+                println!("{}", err);
+            }
+        }
+    }
+
+    if changed {
+        write_album(path_file, &album)?;
+    }
+    Ok(())
+}
+
+/// Writes a `NN.waveform.svg` image for every track with a wave into its album directory.
+///
+/// A `NN.waveform.png` is written next to it when `png` is set.
+pub fn render_library_waves(
+    config: &Config,
+    path_library: &str,
+    library: &Library,
+    options: &RenderOptions,
+    png: bool,
+) -> Result<(), Error> {
+    for album in &library.albums {
+        let path_album = get_path_album(path_library, album);
+        if !Path::new(&path_album).exists() {
+            println!("Skipping missing album: {}", path_album);
+            continue;
+        }
+        for (track_position, track) in album.tracks.iter() {
+            let wave = match &track.wave {
+                Some(wave) => wave,
+                None => {
+                    if config.debug {
+                        println!("Skipping track without wave: {}: {}", track_position, track.name);
+                    }
+                    continue;
+                }
+            };
+            let path_svg = format!("{}{:0>2}.waveform.svg", path_album, track_position);
+            if config.debug {
+                println!("{}", path_svg);
+            }
+            write(&path_svg, render_svg(wave, options))?;
+            if png {
+                let path_png = format!("{}{:0>2}.waveform.png", path_album, track_position);
+                write(&path_png, render_png(wave, options)?)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,167 @@
+// Draws waveforms as images, eg. thumbnails for the web frontend
+use std::io::{Cursor, Error, ErrorKind};
+
+use image::{ImageOutputFormat, Rgba, RgbaImage};
+
+use crate::Wave;
+
+/// How the points of a wave are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaveStyle {
+    /// Drawn from the center line, up and down, like soundcloud.
+    Mirrored,
+    /// Drawn from the bottom up.
+    OneSided,
+}
+
+impl WaveStyle {
+    pub fn parse(style: &str) -> Result<WaveStyle, Error> {
+        match style {
+            "mirrored" => Ok(WaveStyle::Mirrored),
+            "one-sided" => Ok(WaveStyle::OneSided),
+            _ => Err(Error::new(ErrorKind::InvalidInput, format!("Unknown wave style: {}", style))),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Color of the wave, eg. `#ff5500`.
+    pub color: String,
+    /// Color behind the wave, transparent when not set.
+    pub background: Option<String>,
+    pub style: WaveStyle,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            width: 1800,
+            height: 280,
+            color: String::from("#333333"),
+            background: None,
+            style: WaveStyle::Mirrored,
+        }
+    }
+}
+
+/// Returns the top and bottom of the wave at a point, in pixels from the top.
+fn get_span(point: u8, options: &RenderOptions) -> (f32, f32) {
+    let height = options.height as f32;
+    let amplitude = point as f32 / u8::MAX as f32;
+    match options.style {
+        WaveStyle::Mirrored => {
+            let half = amplitude * height / 2.0;
+            (height / 2.0 - half, height / 2.0 + half)
+        }
+        WaveStyle::OneSided => (height - amplitude * height, height),
+    }
+}
+
+/// Renders a wave as an SVG document.
+///
+/// # Example
+/// ```
+/// use yar::waveform::render::{render_svg, RenderOptions, WaveStyle};
+/// let wave = yar::Wave { length: 2, points: vec![255, 0] };
+/// let options = RenderOptions { width: 2, height: 10, style: WaveStyle::OneSided, ..Default::default() };
+/// let svg = render_svg(&wave, &options);
+/// assert!(svg.contains(r#"d="M0 0 L1 0 L1 10 L2 10 L1 10 L0 10 Z""#));
+/// ```
+pub fn render_svg(wave: &Wave, options: &RenderOptions) -> String {
+    let count = wave.points.len().max(1) as f32;
+    let step = options.width as f32 / count;
+
+    // The outline goes along the top of the wave and back along the bottom
+    let mut tops: Vec<String> = vec![];
+    let mut bottoms: Vec<String> = vec![];
+    for (idx, point) in wave.points.iter().enumerate() {
+        let (top, bottom) = get_span(*point, options);
+        let x_start = idx as f32 * step;
+        let x_end = x_start + step;
+        tops.push(format!("{} {}", format_number(x_start), format_number(top)));
+        tops.push(format!("{} {}", format_number(x_end), format_number(top)));
+        bottoms.push(format!("{} {}", format_number(x_start), format_number(bottom)));
+        bottoms.push(format!("{} {}", format_number(x_end), format_number(bottom)));
+    }
+    bottoms.reverse();
+    let mut outline: Vec<String> = tops.into_iter().chain(bottoms).collect();
+    // Consecutive points of the same height would otherwise each add a corner
+    outline.dedup();
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#,
+        options.width, options.height, options.width, options.height
+    );
+    svg.push('\n');
+    if let Some(background) = &options.background {
+        svg.push_str(&format!(r#"<rect width="100%" height="100%" fill="{}"/>"#, background));
+        svg.push('\n');
+    }
+    if !outline.is_empty() {
+        svg.push_str(&format!(r#"<path fill="{}" d="M{} Z"/>"#, options.color, outline.join(" L")));
+        svg.push('\n');
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Renders a wave as a PNG image.
+pub fn render_png(wave: &Wave, options: &RenderOptions) -> Result<Vec<u8>, Error> {
+    let color = parse_color(&options.color)?;
+    let background = match &options.background {
+        Some(background) => parse_color(background)?,
+        None => Rgba([0, 0, 0, 0]),
+    };
+    let mut image = RgbaImage::from_pixel(options.width, options.height, background);
+
+    let count = wave.points.len();
+    if count > 0 {
+        for x in 0..options.width {
+            // Every point that falls into this column, so narrow images keep the peaks
+            let start = x as usize * count / options.width as usize;
+            let end = ((x as usize + 1) * count / options.width as usize).max(start + 1).min(count);
+            let point = wave.points[start.min(count - 1)..end].iter().max().copied().unwrap_or(0);
+            let (top, bottom) = get_span(point, options);
+            let top = top.floor() as u32;
+            let bottom = (bottom.ceil() as u32).min(options.height);
+            for y in top..bottom {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+
+    let mut bytes = Cursor::new(vec![]);
+    match image.write_to(&mut bytes, ImageOutputFormat::Png) {
+        Ok(_) => Ok(bytes.into_inner()),
+        Err(err) => Err(Error::other(err.to_string())),
+    }
+}
+
+/// Parses a `#rrggbb` or `#rrggbbaa` color.
+///
+/// # Example
+/// ```
+/// let color = yar::waveform::render::parse_color("#ff5500").unwrap();
+/// assert_eq!(color.0, [255, 85, 0, 255]);
+/// ```
+pub fn parse_color(color: &str) -> Result<Rgba<u8>, Error> {
+    let invalid = || Error::new(ErrorKind::InvalidInput, format!("Invalid color: {}", color));
+    let hex = color.strip_prefix('#').ok_or_else(invalid)?;
+    if !matches!(hex.len(), 6 | 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    let mut channels = [u8::MAX; 4];
+    for (idx, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+        *channel = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+    Ok(Rgba(channels))
+}
+
+/// Formats a coordinate without trailing zeros.
+fn format_number(number: f32) -> String {
+    let formatted = format!("{:.2}", number);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs::{read, write};
+
+use common::make_temp_dir;
+use yar::cache::{get_cache_key, Cache};
+
+fn make_download(path_dir: &str, name: &str, size: usize) -> String {
+    let path = format!("{}/{}", path_dir, name);
+    write(&path, vec![name.as_bytes()[0]; size]).unwrap();
+    path
+}
+
+#[test]
+fn cached_download_is_copied_out() {
+    let dir = make_temp_dir();
+    let path_dir = dir.path().display().to_string();
+    let path_cache = format!("{}/cache", path_dir);
+    let path_download = make_download(&path_dir, "a.mp3", 100);
+
+    let mut cache = Cache::open(&path_cache, 1).unwrap();
+    cache.insert("https://youtu.be/a", "mp3", &path_download).unwrap();
+
+    // The index is kept between runs
+    let mut cache = Cache::open(&path_cache, 1).unwrap();
+    let path_out = format!("{}/out.mp3", path_dir);
+    assert!(cache.get("https://youtu.be/a", "mp3", &path_out).unwrap());
+    assert_eq!(read(&path_out).unwrap(), read(&path_download).unwrap());
+    assert!(!cache.get("https://youtu.be/a", "flac", &path_out).unwrap());
+    assert!(!cache.get("https://youtu.be/b", "mp3", &path_out).unwrap());
+}
+
+#[test]
+fn least_recently_used_is_evicted() {
+    let dir = make_temp_dir();
+    let path_dir = dir.path().display().to_string();
+    let path_cache = format!("{}/cache", path_dir);
+    let size = 400 * 1024;
+
+    // 1 MB fits two downloads
+    let mut cache = Cache::open(&path_cache, 1).unwrap();
+    cache.insert("https://youtu.be/a", "mp3", &make_download(&path_dir, "a.mp3", size)).unwrap();
+    cache.insert("https://youtu.be/b", "mp3", &make_download(&path_dir, "b.mp3", size)).unwrap();
+    let mut index: serde_yaml::Value = serde_yaml::from_slice(&read(format!("{}/index.yml", path_cache)).unwrap()).unwrap();
+    // Timestamps are in seconds, so make a the oldest by hand
+    index["entries"][get_cache_key("https://youtu.be/a", "mp3")]["used"] = serde_yaml::Value::from(0);
+    write(format!("{}/index.yml", path_cache), serde_yaml::to_string(&index).unwrap()).unwrap();
+
+    let mut cache = Cache::open(&path_cache, 1).unwrap();
+    cache.insert("https://youtu.be/c", "mp3", &make_download(&path_dir, "c.mp3", size)).unwrap();
+    let urls: Vec<&str> = cache.entries().values().map(|entry| entry.url.as_str()).collect();
+    assert_eq!(urls.len(), 2);
+    assert!(!urls.contains(&"https://youtu.be/a"));
+    assert!(cache.size() <= 1024 * 1024);
+
+    // Larger than the whole cache
+    cache.insert("https://youtu.be/d", "mp3", &make_download(&path_dir, "d.mp3", 2 * 1024 * 1024)).unwrap();
+    assert_eq!(cache.entries().len(), 2);
+}
+
+#[test]
+fn verify_removes_broken_entries() {
+    let dir = make_temp_dir();
+    let path_dir = dir.path().display().to_string();
+    let path_cache = format!("{}/cache", path_dir);
+
+    let mut cache = Cache::open(&path_cache, 1).unwrap();
+    cache.insert("https://youtu.be/a", "mp3", &make_download(&path_dir, "a.mp3", 100)).unwrap();
+    cache.insert("https://youtu.be/b", "mp3", &make_download(&path_dir, "b.mp3", 100)).unwrap();
+    let key = get_cache_key("https://youtu.be/a", "mp3");
+    write(format!("{}/{}.mp3", path_cache, key), vec![0; 100]).unwrap();
+
+    let broken = cache.verify().unwrap();
+    assert_eq!(broken, vec![(String::from("https://youtu.be/a"), String::from("checksum does not match"))]);
+    assert_eq!(cache.entries().len(), 1);
+
+    cache.clear().unwrap();
+    assert_eq!(Cache::open(&path_cache, 1).unwrap().entries().len(), 0);
+}
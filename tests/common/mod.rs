@@ -0,0 +1,71 @@
+// Fixtures shared by the integration tests, each test file uses a part of them
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+
+use tempfile::TempDir;
+use yar::{Album, Genres, Library, Location, Track};
+
+/// A track with only a name.
+pub fn make_track(name: &str) -> Track {
+    Track {
+        name: String::from(name),
+        duration: None,
+        artists: None,
+        artist: None,
+        location: vec![],
+        sample: None,
+        lyrics: None,
+        wave: None,
+        genre: None,
+    }
+}
+
+/// A track downloaded from a single location.
+pub fn make_track_at(name: &str, url: &str) -> Track {
+    Track {
+        location: vec![Location { url: String::from(url), at: None }],
+        ..make_track(name)
+    }
+}
+
+/// A phonk album released on 2022-01-01 without a cover, with its tracks by position.
+pub fn make_album(artist: &str, name: &str, tracks: Vec<(&str, Track)>) -> Album {
+    Album {
+        name: String::from(name),
+        artist: String::from(artist),
+        genre: Genres(vec![String::from("phonk")]),
+        released: chrono::NaiveDate::from_ymd_opt(2022, 1, 1).unwrap(),
+        cover: String::new(),
+        track_count: tracks.len() as i8,
+        tracks: tracks
+            .into_iter()
+            .map(|(track_position, track)| (String::from(track_position), track))
+            .collect::<HashMap<String, Track>>(),
+    }
+}
+
+/// A library of one album per artist, with tracks at the given positions.
+pub fn make_library(artists: &[(&str, &[&str])]) -> Library {
+    let albums = artists
+        .iter()
+        .map(|(artist, track_positions)| {
+            let tracks = track_positions
+                .iter()
+                .map(|track_position| (*track_position, make_track("Track")))
+                .collect();
+            make_album(artist, "Album", tracks)
+        })
+        .collect();
+    Library { albums }
+}
+
+/// A directory of its own for a test, removed when it is dropped.
+pub fn make_temp_dir() -> TempDir {
+    tempfile::Builder::new().prefix("yar-").tempdir().unwrap()
+}
+
+/// Returns the path of a temporary directory with a trailing `/`, like library directories.
+pub fn get_path(dir: &TempDir) -> String {
+    format!("{}/", dir.path().display())
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use std::fs::write;
+
+use common::{get_path, make_temp_dir};
+use yar::config::{read_settings_with_env, Settings};
+use yar::transcoder::read_profiles;
+
+#[test]
+fn config_file_overrides_defaults() {
+    let dir = make_temp_dir();
+    let path_config = format!("{}config.yml", get_path(&dir));
+    write(&path_config, "format: flac\ncover_size: 600\ncache: null\n# not a setting\nunknown: 4\n").unwrap();
+
+    let settings = read_settings_with_env(Some(&path_config), vec![]).unwrap();
+    assert_eq!(settings, Settings {
+        format: String::from("flac"),
+        cover_size: Some(600),
+        cache: None,
+        ..Settings::default()
+    });
+    let config = settings.to_config();
+    assert_eq!(config.audio_fmt, "flac");
+    assert!(config.download_covers);
+}
+
+#[test]
+fn invalid_config_file_is_an_error() {
+    let dir = make_temp_dir();
+    let path_config = format!("{}config-invalid.yml", get_path(&dir));
+    write(&path_config, "cover_size: big\n").unwrap();
+    assert!(read_settings_with_env(Some(&path_config), vec![]).is_err());
+
+    write(&path_config, "format: wav\n").unwrap();
+    assert!(read_settings_with_env(Some(&path_config), vec![]).is_err());
+
+    assert!(read_settings_with_env(Some("/nonexistent/config.yml"), vec![]).is_err());
+}
+
+#[test]
+fn environment_overrides_config_file() {
+    let dir = make_temp_dir();
+    let path_config = format!("{}config-env.yml", get_path(&dir));
+    write(&path_config, "format: flac\nretries: 1\n").unwrap();
+
+    let vars = vec![(String::from("YAR_FORMAT"), String::from("opus")), (String::from("HOME"), String::from("/"))];
+    let settings = read_settings_with_env(Some(&path_config), vars).unwrap();
+    assert_eq!((settings.format.as_str(), settings.retries), ("opus", 1));
+
+    let vars = vec![(String::from("YAR_FORMAT"), String::from("wav"))];
+    assert!(read_settings_with_env(Some(&path_config), vars).is_err());
+}
+
+#[test]
+fn profile_with_unknown_format_is_an_error() {
+    let dir = make_temp_dir();
+    let path_profiles = format!("{}profiles.yml", get_path(&dir));
+    write(&path_profiles, "profiles:\n  phone:\n    format: opus\n    bitrate: 96k\n").unwrap();
+    assert_eq!(read_profiles(&path_profiles).unwrap().profiles["phone"].format, "opus");
+
+    write(&path_profiles, "profiles:\n  phone:\n    format: wav\n").unwrap();
+    assert!(read_profiles(&path_profiles).is_err());
+}
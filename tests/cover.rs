@@ -0,0 +1,97 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{get_path, make_album, make_temp_dir};
+use image::{guess_format, ImageFormat, ImageOutputFormat, RgbaImage};
+use yar::cover::{get_cover, get_cover_extension, make_embedded_cover};
+use yar::{Album, Config};
+
+fn make_png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Cursor::new(vec![]);
+    RgbaImage::new(width, height)
+        .write_to(&mut bytes, ImageOutputFormat::Png)
+        .unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn cover_png_is_resized_to_jpeg() {
+    let png = make_png(100, 50);
+    assert_eq!(get_cover_extension(&png), "png");
+
+    let embedded = make_embedded_cover(&png, Some(20)).unwrap();
+    assert_eq!(guess_format(&embedded).unwrap(), ImageFormat::Jpeg);
+    let image = image::load_from_memory(&embedded).unwrap();
+    assert_eq!((image.width(), image.height()), (20, 10));
+}
+
+#[test]
+fn cover_small_jpeg_is_kept() {
+    let png = make_png(10, 10);
+    let jpeg = make_embedded_cover(&png, None).unwrap();
+    assert_eq!(make_embedded_cover(&jpeg, Some(20)).unwrap(), jpeg);
+}
+
+#[test]
+fn cover_is_read_from_disk_when_cached() {
+    let dir = make_temp_dir();
+    let path_album = get_path(&dir);
+    let png = make_png(10, 10);
+    std::fs::write(format!("{}cover.png", path_album), &png).unwrap();
+    std::fs::write(format!("{}cover.url", path_album), "https://example.com/cover.png").unwrap();
+
+    let album = Album {
+        cover: String::from("https://example.com/cover.png"),
+        ..make_album("artist", "Album", vec![])
+    };
+    let config = Config::default();
+    assert_eq!(get_cover(&config, &path_album, &album).unwrap(), png);
+}
+
+#[test]
+fn cover_falls_back_to_disk_when_download_fails() {
+    let dir = make_temp_dir();
+    let path_album = get_path(&dir);
+    let png = make_png(10, 10);
+    std::fs::write(format!("{}cover.png", path_album), &png).unwrap();
+    std::fs::write(format!("{}cover.url", path_album), "file:///old/cover.png").unwrap();
+
+    let album = Album {
+        cover: format!("file://{}missing.png", path_album),
+        ..make_album("artist", "Album", vec![])
+    };
+    let config = Config::default();
+    assert_eq!(get_cover(&config, &path_album, &album).unwrap(), png);
+    std::fs::remove_file(format!("{}cover.png", path_album)).unwrap();
+    assert!(get_cover(&config, &path_album, &album).is_err());
+}
+
+#[test]
+fn cover_on_disk_is_used_without_downloading_covers() {
+    let dir = make_temp_dir();
+    let path_album = get_path(&dir);
+    let png = make_png(10, 10);
+    // Saved by hand, so there is no cover.url
+    std::fs::write(format!("{}cover.png", path_album), &png).unwrap();
+
+    let album = Album {
+        cover: String::from("file:///nonexistent/cover.png"),
+        ..make_album("artist", "Album", vec![])
+    };
+    let config = Config { download_covers: false, ..Config::default() };
+    assert_eq!(get_cover(&config, &path_album, &album).unwrap(), png);
+}
+
+#[test]
+fn cover_without_url_is_not_downloaded() {
+    let dir = make_temp_dir();
+    let path_album = get_path(&dir);
+
+    let album = make_album("artist", "Album", vec![]);
+    let config = Config::default();
+    assert!(get_cover(&config, &path_album, &album).unwrap().is_empty());
+    let png = make_png(10, 10);
+    std::fs::write(format!("{}cover.png", path_album), &png).unwrap();
+    assert_eq!(get_cover(&config, &path_album, &album).unwrap(), png);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::fs::{create_dir_all, remove_file, write};
+
+use common::{make_album, make_temp_dir, make_track_at};
+use yar::db::scan_db;
+use yar::importer::get_path_album_file;
+use yar::{find_artist, Album, Library, LibrarySource};
+
+fn make_album_at(artist: &str, name: &str, url: &str) -> Album {
+    make_album(artist, name, vec![("1", make_track_at("Track", url))])
+}
+
+fn write_album(path_db: &str, album: &Album) {
+    let path = get_path_album_file(path_db, album);
+    create_dir_all(std::path::Path::new(&path).parent().unwrap()).unwrap();
+    write(path, serde_yaml::to_string(album).unwrap()).unwrap();
+}
+
+#[test]
+fn db_is_scanned_with_its_repos() {
+    let dir = make_temp_dir();
+    let path_db = dir.path().display().to_string();
+    write_album(&path_db, &make_album_at("Artist", "First", "https://soundcloud.com/artist/first"));
+    write_album(&format!("{}/repo/other", path_db), &make_album_at("Other Artist", "Second", "https://soundcloud.com/other/second"));
+    // Hidden files and repos are skipped
+    write(format!("{}/artists/artist/.draft.yml", path_db), "not an album").unwrap();
+    create_dir_all(format!("{}/repo/.git", path_db)).unwrap();
+
+    let library = scan_db(&path_db).unwrap();
+    let mut names: Vec<&str> = library.albums.iter().map(|album| album.name.as_str()).collect();
+    names.sort();
+    assert_eq!(names, vec!["First", "Second"]);
+    assert_eq!(find_artist(&library, "soundcloud.com/other").unwrap().artist, "Other Artist");
+    assert!(find_artist(&library, "soundcloud.com/nobody").is_none());
+}
+
+#[test]
+fn db_albums_are_read_one_at_a_time() {
+    let dir = make_temp_dir();
+    let path_db = dir.path().display().to_string();
+    write_album(&path_db, &make_album_at("Artist", "First", "https://soundcloud.com/artist/first"));
+    write_album(&path_db, &make_album_at("Artist", "Second", "https://soundcloud.com/artist/second"));
+    write(format!("{}/artists/artist/broken.yml", path_db), "name: [").unwrap();
+
+    // A broken album file doesn't stop the others from being read
+    let albums: Vec<_> = LibrarySource::Db(path_db.clone()).albums().unwrap().collect();
+    assert_eq!(albums.len(), 3);
+    assert_eq!(albums.iter().filter(|album| album.is_ok()).count(), 2);
+    assert!(LibrarySource::Db(path_db.clone()).read().is_err());
+
+    // The same albums as a library file
+    remove_file(format!("{}/artists/artist/broken.yml", path_db)).unwrap();
+    let library = LibrarySource::Db(path_db.clone()).read().unwrap();
+    let path_file = format!("{}/library.yml", path_db);
+    write(&path_file, serde_yaml::to_string(&Library { albums: library.albums }).unwrap()).unwrap();
+    let names: Vec<String> = LibrarySource::File(path_file).albums().unwrap().map(|album| album.unwrap().name).collect();
+    assert_eq!(names.len(), 2);
+
+    assert!(LibrarySource::Db(format!("{}/nothing", path_db)).albums().is_err());
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use common::make_library;
+use yar::fingerprint::{find_duplicates, make_fingerprint, Fingerprint, SIMILARITY_SAME};
+use yar::lockfile::{prune_lockfile, LockedTrack, Lockfile};
+
+const SAMPLE_RATE: f64 = 11025.0;
+
+/// A pseudo random melody of notes between 300 and 2000 Hz, 10 seconds long.
+fn make_melody(seed: u64, volume: f64, noise: f64) -> Vec<i16> {
+    let mut state = seed;
+    let mut random = move || {
+        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (state >> 33) as f64 / (1u64 << 31) as f64
+    };
+    let notes: Vec<f64> = (0..40).map(|_| 300.0 + random() * 1700.0).collect();
+    let mut noise_state = 7u64;
+    (0..(SAMPLE_RATE * 10.0) as usize)
+        .map(|idx| {
+            let time = idx as f64 / SAMPLE_RATE;
+            let frequency = notes[(time * 4.0) as usize];
+            noise_state = noise_state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            let hiss = ((noise_state >> 33) as f64 / (1u64 << 31) as f64 - 0.5) * noise;
+            let sample = (2.0 * std::f64::consts::PI * frequency * time).sin() * volume + hiss;
+            (sample * i16::MAX as f64 * 0.5) as i16
+        })
+        .collect()
+}
+
+#[test]
+fn fingerprint_matches_quieter_noisy_copy() {
+    let original = make_fingerprint(&make_melody(1, 1.0, 0.0));
+    let copy = make_fingerprint(&make_melody(1, 0.5, 0.05));
+    let other = make_fingerprint(&make_melody(2, 1.0, 0.0));
+    assert!(original.similarity(&copy) >= SIMILARITY_SAME);
+    assert!(original.similarity(&other) < SIMILARITY_SAME);
+}
+
+#[test]
+fn fingerprint_finds_duplicates_in_lockfile() {
+    let locked = |fingerprint: &Fingerprint| LockedTrack {
+        source: None,
+        fingerprint: Some(fingerprint.encode()),
+    };
+    let mut lockfile = Lockfile::default();
+    let original = make_fingerprint(&make_melody(1, 1.0, 0.0));
+    // Split a little later, like the same track cut from a full album upload
+    let cut = make_fingerprint(&make_melody(1, 0.8, 0.0)[4096..]);
+    let other = make_fingerprint(&make_melody(3, 1.0, 0.0));
+    lockfile.tracks.insert(String::from("a/album/1"), locked(&original));
+    lockfile.tracks.insert(String::from("b/album/4"), locked(&cut));
+    lockfile.tracks.insert(String::from("c/album/2"), locked(&other));
+
+    let library = make_library(&[("a", &["1"]), ("b", &["4"]), ("c", &["2"])]);
+    let duplicates = find_duplicates(&mut lockfile, &library);
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!((duplicates[0].0.as_str(), duplicates[0].1.as_str()), ("a/album/1", "b/album/4"));
+}
+
+#[test]
+fn fingerprint_compares_tracks_shorter_than_the_overlap() {
+    let short = make_fingerprint(&make_melody(1, 1.0, 0.0)[..4096 * 4]);
+    assert!(short.0.len() < 16);
+    assert_eq!(short.similarity(&short), 1.0);
+}
+
+#[test]
+fn lockfile_drops_tracks_removed_from_the_library() {
+    let mut lockfile = Lockfile::default();
+    lockfile.tracks.insert(String::from("a/album/1"), LockedTrack::default());
+    lockfile.tracks.insert(String::from("a/album/2"), LockedTrack::default());
+
+    let removed = prune_lockfile(&mut lockfile, &make_library(&[("a", &["1"])]));
+    assert_eq!(removed, vec![String::from("a/album/2")]);
+    assert_eq!(lockfile.tracks.keys().collect::<Vec<_>>(), vec!["a/album/1"]);
+}
@@ -0,0 +1,310 @@
+mod common;
+
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::io::{Error, ErrorKind};
+
+use common::make_temp_dir;
+use yar::importer::bandcamp::{
+    extract_json_from_html, extract_release_urls, get_album_from_url, get_bandcamp_album,
+    is_discography_url, parse_artists, parse_duration,
+};
+use yar::importer::merge::{merge_album, Change};
+use yar::importer::soundcloud::{
+    soundcloud_extract_json_from_html, soundcloud_get, soundcloud_parse_json,
+    soundcloud_parse_sound, SoundcloudResource,
+};
+use yar::importer::folder::import_folder;
+use yar::importer::{read_album, Fetcher};
+use yar::{Genres, Location, Sample};
+
+/// Serves saved pages from `tests/fixtures` instead of the network.
+struct FixtureFetcher {
+    pages: HashMap<&'static str, &'static str>,
+}
+
+impl FixtureFetcher {
+    fn new(pages: &[(&'static str, &'static str)]) -> Self {
+        FixtureFetcher { pages: pages.iter().cloned().collect() }
+    }
+}
+
+impl Fetcher for FixtureFetcher {
+    fn fetch(&self, url: &str) -> Result<String, Error> {
+        match self.pages.get(url) {
+            Some(name) => Ok(fixture(name)),
+            None => Err(Error::new(ErrorKind::NotFound, format!("No fixture for {}", url))),
+        }
+    }
+}
+
+fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    read_to_string(&path).unwrap_or_else(|_| panic!("missing fixture: {}", path))
+}
+
+#[test]
+fn bandcamp_extract_json_from_html() {
+    let json = extract_json_from_html(&fixture("bandcamp_album.html")).unwrap();
+    let album = get_bandcamp_album(&json).unwrap();
+
+    assert_eq!(album.name, "Sensations");
+    assert_eq!(album.by_artist.name, "backwhen");
+    assert_eq!(album.num_tracks, 3);
+    assert_eq!(album.track.item_list_element.len(), 3);
+}
+
+#[test]
+fn bandcamp_parse_duration() {
+    assert_eq!(parse_duration("P00H03M21S").unwrap(), "00:03:21");
+    assert_eq!(parse_duration("P01H00M05S").unwrap(), "01:00:05");
+}
+
+#[test]
+fn bandcamp_parse_artists() {
+    let json = extract_json_from_html(&fixture("bandcamp_album.html")).unwrap();
+    let album = get_bandcamp_album(&json).unwrap();
+    let tracks = &album.track.item_list_element;
+
+    assert!(parse_artists(&tracks[0].item).is_none());
+
+    let ids: Vec<String> = parse_artists(&tracks[1].item)
+        .unwrap()
+        .into_iter()
+        .map(|artist| artist.id)
+        .collect();
+    assert_eq!(ids, vec!["backwhen", "Dxrk", "Ghostface 600"]);
+}
+
+#[test]
+fn bandcamp_album_from_url() {
+    let url = "https://backwhen.bandcamp.com/album/sensations";
+    let fetcher = &FixtureFetcher::new(&[(url, "bandcamp_album.html")]);
+    let album = get_album_from_url(fetcher, url).unwrap();
+
+    assert_eq!(album.name, "Sensations");
+    assert_eq!(album.released.to_string(), "2022-08-04");
+    assert_eq!(album.genre.0, vec!["Electronic", "phonk", "drift phonk", "memphis"]);
+    assert_eq!(album.tracks.len(), 3);
+    let track = &album.tracks["3"];
+    assert_eq!(track.name, "Night Drive");
+    assert_eq!(track.location[0].url, "https://backwhen.bandcamp.com/track/night-drive");
+}
+
+#[test]
+fn bandcamp_track_release_from_url() {
+    let url = "https://backwhen.bandcamp.com/track/lost-tape";
+    let fetcher = &FixtureFetcher::new(&[(url, "bandcamp_track.html")]);
+    let album = get_album_from_url(fetcher, url).unwrap();
+
+    assert_eq!(album.name, "Lost Tape");
+    assert_eq!(album.track_count, 1);
+    assert_eq!(album.tracks["1"].location[0].url, url);
+}
+
+#[test]
+fn bandcamp_extract_release_urls() {
+    let urls = extract_release_urls("https://backwhen.bandcamp.com", &fixture("bandcamp_music.html"));
+
+    assert_eq!(urls, vec![
+        "https://backwhen.bandcamp.com/album/sensations",
+        "https://backwhen.bandcamp.com/track/lost-tape",
+        "https://guest.bandcamp.com/album/split-ep",
+        "https://backwhen.bandcamp.com/album/old-tape",
+    ]);
+}
+
+#[test]
+fn bandcamp_discography_urls() {
+    assert!(is_discography_url("https://backwhen.bandcamp.com"));
+    assert!(is_discography_url("https://backwhen.bandcamp.com/music?sort=new"));
+    assert!(!is_discography_url("https://backwhen.bandcamp.com/album/sensations"));
+    assert!(!is_discography_url("backwhen.bandcamp.com/music"));
+    assert!(!is_discography_url(""));
+}
+
+#[test]
+fn soundcloud_invalid_date_is_an_error() {
+    let url = "https://soundcloud.com/kordhell/murder-in-my-mind";
+    let fetcher = &FixtureFetcher::new(&[
+        ("https://wave.sndcdn.com/murder_m.json", "soundcloud_wave.json"),
+    ]);
+    let html = fixture("soundcloud_sound.html").replace("2022-02-14T18:22:19Z", "last tuesday");
+    let json = soundcloud_extract_json_from_html(&html).unwrap();
+    let sound = match soundcloud_parse_json(&json).unwrap() {
+        SoundcloudResource::Sound(sound) => sound,
+        SoundcloudResource::Playlist(_) => panic!("expected a sound"),
+    };
+    assert!(soundcloud_parse_sound(fetcher, url, sound).is_err());
+}
+
+#[test]
+fn soundcloud_parse_sound_from_fixture() {
+    let url = "https://soundcloud.com/kordhell/murder-in-my-mind";
+    let fetcher = &FixtureFetcher::new(&[
+        ("https://wave.sndcdn.com/murder_m.json", "soundcloud_wave.json"),
+    ]);
+    let json = soundcloud_extract_json_from_html(&fixture("soundcloud_sound.html")).unwrap();
+    let sound = match soundcloud_parse_json(&json).unwrap() {
+        SoundcloudResource::Sound(sound) => sound,
+        SoundcloudResource::Playlist(_) => panic!("expected a sound"),
+    };
+    let album = soundcloud_parse_sound(fetcher, url, sound).unwrap();
+
+    assert_eq!(album.name, "Murder In My Mind");
+    assert_eq!(album.artist, "KORDHELL");
+    assert_eq!(album.cover, "https://i1.sndcdn.com/artworks-000123-abc-t500x500.jpg");
+    let track = &album.tracks["1"];
+    assert_eq!(track.duration.as_deref(), Some("0:2:24"));
+    assert_eq!(track.location[0].url, url);
+    let wave = track.wave.as_ref().unwrap();
+    assert_eq!(wave.length, 8);
+    assert_eq!(wave.points, vec![0, 25, 63, 127, 191, 255, 127, 12]);
+}
+
+#[test]
+fn soundcloud_playlist_from_url() {
+    let url = "https://soundcloud.com/djsmokey/sets/memphis-tape-vol-1";
+    let fetcher = &FixtureFetcher::new(&[
+        (url, "soundcloud_playlist.html"),
+        ("https://wave.sndcdn.com/intro_m.json", "soundcloud_wave.json"),
+        ("https://wave.sndcdn.com/late_m.json", "soundcloud_wave.json"),
+        ("https://api-v2.soundcloud.com/tracks?ids=3,4&client_id=abcClientId", "soundcloud_tracks.json"),
+        ("https://wave.sndcdn.com/hiss_m.json", "soundcloud_wave.json"),
+        ("https://wave.sndcdn.com/outro_m.json", "soundcloud_wave.json"),
+    ]);
+    let album = soundcloud_get(fetcher, url).unwrap();
+
+    assert_eq!(album.name, "Memphis Tape Vol. 1");
+    assert_eq!(album.released.to_string(), "2021-10-31");
+    assert_eq!(album.cover, "https://i1.sndcdn.com/artworks-000201-aaa-t500x500.jpg");
+    assert_eq!(album.track_count, 4);
+    assert_eq!(album.genre.0, vec!["Memphis Rap"]);
+    // Fetched from the API, in the order of the set
+    assert_eq!(album.tracks["3"].name, "Tape Hiss");
+    assert_eq!(album.tracks["4"].name, "Outro");
+    assert!(album.tracks["1"].genre.is_none());
+    let track = &album.tracks["2"];
+    assert_eq!(track.name, "Late Night");
+    assert_eq!(track.duration.as_deref(), Some("0:3:21"));
+    assert_eq!(track.artists.as_ref().unwrap()[0].id, "Guest MC");
+    assert_eq!(track.location[0].url, "https://soundcloud.com/guest-mc/late-night");
+}
+
+#[test]
+fn merge_keeps_curated_data() {
+    let url = "https://backwhen.bandcamp.com/album/sensations";
+    let fetcher = &FixtureFetcher::new(&[(url, "bandcamp_album.html")]);
+    let mut existing = get_album_from_url(fetcher, url).unwrap();
+    existing.tracks.remove("3");
+    existing.track_count = 2;
+    existing.cover = String::from("https://example.com/old.jpg");
+    let track = existing.tracks.get_mut("2").unwrap();
+    track.name = String::from("Sensation");
+    track.lyrics = Some(String::from("la la la"));
+    track.sample = Some(vec![Sample {
+        artist: String::from("Three 6 Mafia"),
+        name: String::from("Tear da Club Up"),
+        r#type: String::from("vocal"),
+    }]);
+    track.location.push(Location { url: String::from("https://youtu.be/mirror"), at: None });
+
+    let scraped = get_album_from_url(fetcher, url).unwrap();
+    let changes = merge_album(&mut existing, scraped);
+
+    assert_eq!(changes, vec![
+        Change::Cover(String::from("https://example.com/old.jpg"), String::from("https://f4.bcbits.com/img/a1234567890_10.jpg")),
+        Change::TrackCount(2, 3),
+        Change::TrackName(String::from("2"), String::from("Sensation"), String::from("Sensations")),
+        Change::TrackAdded(String::from("3"), String::from("Night Drive")),
+    ]);
+    let track = &existing.tracks["2"];
+    assert_eq!(track.name, "Sensations");
+    assert_eq!(track.lyrics.as_deref(), Some("la la la"));
+    assert_eq!(track.sample.as_ref().unwrap().len(), 1);
+    assert_eq!(track.location.len(), 2);
+    assert_eq!(existing.tracks["3"].name, "Night Drive");
+}
+
+#[test]
+fn merge_matches_padded_positions_and_writes_tracks_in_order() {
+    let url = "https://backwhen.bandcamp.com/album/sensations";
+    let fetcher = &FixtureFetcher::new(&[(url, "bandcamp_album.html")]);
+    let mut existing = get_album_from_url(fetcher, url).unwrap();
+    let track = existing.tracks.remove("1").unwrap();
+    existing.tracks.insert(String::from("01"), track);
+
+    let scraped = get_album_from_url(fetcher, url).unwrap();
+    assert_eq!(merge_album(&mut existing, scraped), vec![]);
+    assert!(existing.tracks.contains_key("01"));
+
+    existing.tracks.insert(String::from("10"), existing.tracks["3"].clone());
+    let yaml: serde_yaml::Value = serde_yaml::from_str(&serde_yaml::to_string(&existing).unwrap()).unwrap();
+    let positions: Vec<&str> = yaml["tracks"]
+        .as_mapping()
+        .unwrap()
+        .iter()
+        .map(|(position, _)| position.as_str().unwrap())
+        .collect();
+    assert_eq!(positions, vec!["01", "2", "3", "10"]);
+}
+
+fn write_tagged_mp3(path: &str, title: &str, artist: &str, track: u32, comment: Option<&str>) {
+    use id3::TagLike;
+    std::fs::write(path, []).unwrap();
+    let mut tag = id3::Tag::new();
+    tag.set_title(title);
+    tag.set_artist(artist);
+    tag.set_album_artist("Album Artist");
+    tag.set_album("Hand Tagged");
+    tag.set_track(track);
+    tag.set_total_tracks(3);
+    tag.set_year(2019);
+    tag.set_genre("phonk");
+    if let Some(comment) = comment {
+        tag.add_frame(id3::frame::Comment {
+            lang: String::from("eng"),
+            description: String::new(),
+            text: String::from(comment),
+        });
+    }
+    tag.add_frame(id3::frame::Picture {
+        mime_type: String::from("image/png"),
+        picture_type: id3::frame::PictureType::CoverFront,
+        description: String::new(),
+        data: vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+    });
+    tag.write_to_path(path, id3::Version::Id3v24).unwrap();
+}
+
+#[test]
+fn folder_import_from_tagged_files() {
+    let dir_in = make_temp_dir();
+    let dir_out = make_temp_dir();
+    let path_in = dir_in.path().display().to_string();
+    let path_out = dir_out.path().display().to_string();
+    std::fs::create_dir_all(format!("{}/disc", path_in)).unwrap();
+    write_tagged_mp3(&format!("{}/a.mp3", path_in), "First", "Album Artist", 1, Some("SAMPLE: DJ Screw, Sippin Codeine"));
+    write_tagged_mp3(&format!("{}/disc/b.mp3", path_in), "Second", "Guest", 2, None);
+
+    import_folder(&path_in, &path_out, &Genres::default()).unwrap();
+
+    let album = read_album(&format!("{}/artists/album-artist/hand-tagged.yml", path_out)).unwrap();
+    assert_eq!(album.name, "Hand Tagged");
+    assert_eq!(album.artist, "Album Artist");
+    assert_eq!(album.genre.0, vec!["phonk"]);
+    assert_eq!(album.released.to_string(), "2019-01-01");
+    assert_eq!(album.track_count, 3);
+    assert!(album.cover.starts_with("file://") && album.cover.ends_with("hand-tagged.png"));
+    assert!(std::path::Path::new(album.cover.trim_start_matches("file://")).exists());
+
+    let first = &album.tracks["1"];
+    assert_eq!(first.name, "First");
+    assert!(first.artists.is_none());
+    assert!(first.location[0].url.starts_with("file://") && first.location[0].url.ends_with("/a.mp3"));
+    assert_eq!(first.sample.as_ref().unwrap()[0].name, "Sippin Codeine");
+
+    let second = &album.tracks["2"];
+    assert_eq!(second.artists.as_ref().unwrap()[0].id, "Guest");
+    assert!(second.genre.is_none());
+}
@@ -0,0 +1,265 @@
+mod common;
+
+use std::fs::{create_dir_all, read, write};
+use std::io::Cursor;
+use std::path::Path;
+
+use id3::{frame, TagLike};
+use image::{ImageOutputFormat, RgbImage};
+use yar::audit::{audit_library, IssueKind};
+use yar::waveform::render::RenderOptions;
+use yar::waveform::render_library_waves;
+use common::{get_path, make_album, make_temp_dir, make_track};
+use yar::{find_track, get_path_album, process_library, read_track_id, Album, Config, Library, Sample, Track, Wave};
+
+fn make_config(retag: bool) -> Config<'static> {
+    Config {
+        retag,
+        cache: None,
+        ..Config::default()
+    }
+}
+
+fn make_album_with_cover() -> Album {
+    let track = Track {
+        lyrics: Some(String::from("la la la")),
+        ..make_track("New Name")
+    };
+    Album {
+        cover: String::from("https://example.com/cover.jpg"),
+        ..make_album("Artist", "Album", vec![("1", track)])
+    }
+}
+
+#[test]
+fn retag_renames_and_tags_existing_tracks() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let album = make_album_with_cover();
+    let path_album = get_path_album(&path_library, &album);
+    create_dir_all(&path_album).unwrap();
+    // A cached cover, so nothing is downloaded
+    write(format!("{}cover.jpg", path_album), []).unwrap();
+    write(format!("{}cover.url", path_album), &album.cover).unwrap();
+    write(format!("{}1 - Old Name.mp3", path_album), []).unwrap();
+
+    let library = Library { albums: vec![album] };
+    process_library(&make_config(true), &path_library, &library).unwrap();
+
+    assert!(!Path::new(&format!("{}1 - Old Name.mp3", path_album)).exists());
+    let tag = id3::Tag::read_from_path(format!("{}1 - New Name.mp3", path_album)).unwrap();
+    assert_eq!(tag.title(), Some("New Name"));
+    assert_eq!(tag.album(), Some("Album"));
+    assert_eq!(tag.genre(), Some("phonk"));
+
+    let track_id = read_track_id(&format!("{}1 - New Name.mp3", path_album)).unwrap().unwrap();
+    assert_eq!(track_id, "artist/album/1");
+    let (_, track_position, track) = find_track(&library, &track_id).unwrap();
+    assert_eq!((track_position, track.name.as_str()), ("1", "New Name"));
+}
+
+#[test]
+fn retag_refuses_formats_tagged_by_ffmpeg() {
+    let dir = make_temp_dir();
+    let path_library = format!("{}library/", get_path(&dir));
+    let config = Config { audio_fmt: "flac", ..make_config(true) };
+    let library = Library { albums: vec![make_album_with_cover()] };
+    assert!(process_library(&config, &path_library, &library).is_err());
+    assert!(!Path::new(&path_library).exists());
+}
+
+#[test]
+fn retag_never_downloads_the_cover() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    // Unreachable, and the folder image was downloaded from another URL
+    album.cover = String::from("file:///nonexistent/cover.jpg");
+    let path_album = get_path_album(&path_library, &album);
+    let path_track = format!("{}1 - New Name.mp3", path_album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}cover.jpg", path_album), make_jpeg()).unwrap();
+    write(format!("{}cover.url", path_album), "https://example.com/old.jpg").unwrap();
+    write(&path_track, []).unwrap();
+
+    let library = Library { albums: vec![album] };
+    process_library(&make_config(true), &path_library, &library).unwrap();
+
+    let tag = id3::Tag::read_from_path(&path_track).unwrap();
+    assert_eq!(tag.pictures().next().unwrap().data, make_jpeg());
+    assert_eq!(std::fs::read_to_string(format!("{}cover.url", path_album)).unwrap(), "https://example.com/old.jpg");
+}
+
+#[test]
+fn sync_tags_tracks_when_the_cover_is_missing() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    album.cover = String::from("file:///nonexistent/cover.jpg");
+    let path_album = get_path_album(&path_library, &album);
+    let path_track = format!("{}1 - New Name.mp3", path_album);
+    create_dir_all(&path_album).unwrap();
+    write(&path_track, []).unwrap();
+
+    let library = Library { albums: vec![album] };
+    process_library(&make_config(false), &path_library, &library).unwrap();
+
+    let tag = id3::Tag::read_from_path(&path_track).unwrap();
+    assert_eq!(tag.title(), Some("New Name"));
+    assert_eq!(tag.pictures().count(), 0);
+}
+
+#[test]
+fn sync_goes_on_when_a_track_cant_be_tagged() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let album = make_album_with_cover();
+    let path_album = get_path_album(&path_library, &album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}cover.jpg", path_album), make_jpeg()).unwrap();
+    write(format!("{}cover.url", path_album), &album.cover).unwrap();
+    write(format!("{}1 - New Name.flac", path_album), b"not flac").unwrap();
+
+    let config = Config { audio_fmt: "flac", ..make_config(false) };
+    let library = Library { albums: vec![album] };
+    process_library(&config, &path_library, &library).unwrap();
+}
+
+#[test]
+fn sync_leaves_tagged_tracks_untouched() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let album = make_album_with_cover();
+    let path_album = get_path_album(&path_library, &album);
+    let path_track = format!("{}1 - New Name.mp3", path_album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}cover.jpg", path_album), make_jpeg()).unwrap();
+    write(format!("{}cover.url", path_album), &album.cover).unwrap();
+    write(&path_track, []).unwrap();
+
+    // The first sync tags the existing file, the next ones have nothing to change
+    let library = Library { albums: vec![album] };
+    process_library(&make_config(false), &path_library, &library).unwrap();
+    let modified = std::fs::metadata(&path_track).unwrap().modified().unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    process_library(&make_config(false), &path_library, &library).unwrap();
+    assert_eq!(std::fs::metadata(&path_track).unwrap().modified().unwrap(), modified);
+}
+
+fn make_jpeg() -> Vec<u8> {
+    let mut bytes = Cursor::new(vec![]);
+    RgbImage::new(4, 4)
+        .write_to(&mut bytes, ImageOutputFormat::Jpeg(90))
+        .unwrap();
+    bytes.into_inner()
+}
+
+#[test]
+fn tagging_twice_gives_identical_bytes() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    album.tracks.get_mut("1").unwrap().sample = Some(vec![Sample {
+        artist: String::from("Someone"),
+        name: String::from("Something"),
+        r#type: String::from("sample"),
+    }]);
+    let path_album = get_path_album(&path_library, &album);
+    let path_track = format!("{}1 - New Name.mp3", path_album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}cover.jpg", path_album), make_jpeg()).unwrap();
+    write(format!("{}cover.url", path_album), &album.cover).unwrap();
+    write(&path_track, []).unwrap();
+
+    // Frames from another tagger, in a different language and picture type
+    let mut tag = id3::Tag::new();
+    tag.add_frame(frame::Comment {
+        lang: String::from("eng"),
+        description: String::from("downloader"),
+        text: String::from("Downloaded"),
+    });
+    tag.add_frame(frame::Lyrics {
+        lang: String::from("eng"),
+        description: String::new(),
+        text: String::from("old lyrics"),
+    });
+    tag.add_frame(frame::Picture {
+        mime_type: String::from("image/png"),
+        picture_type: frame::PictureType::Other,
+        description: String::from("thumbnail"),
+        data: vec![1, 2, 3],
+    });
+    tag.write_to_path(&path_track, id3::Version::Id3v24).unwrap();
+
+    let library = Library { albums: vec![album] };
+    process_library(&make_config(true), &path_library, &library).unwrap();
+    let first = read(&path_track).unwrap();
+    process_library(&make_config(true), &path_library, &library).unwrap();
+    let second = read(&path_track).unwrap();
+    assert_eq!(first, second);
+
+    let tag = id3::Tag::read_from_path(&path_track).unwrap();
+    assert_eq!(tag.comments().count(), 1);
+    assert_eq!(tag.lyrics().count(), 1);
+    assert_eq!(tag.pictures().count(), 1);
+    assert_eq!(tag.pictures().next().unwrap().picture_type, frame::PictureType::CoverFront);
+    assert_eq!(tag.lyrics().next().unwrap().text, "la la la");
+}
+
+#[test]
+fn audit_reports_missing_empty_and_extra_files() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    album.tracks.insert(String::from("2"), make_track("Missing"));
+    let path_album = get_path_album(&path_library, &album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}1 - New Name.mp3", path_album), []).unwrap();
+    write(format!("{}3 - Bonus.mp3", path_album), []).unwrap();
+
+    let library = Library { albums: vec![album] };
+    let issues = audit_library(&make_config(false), &path_library, &library, 5.0).unwrap();
+    let mut kinds: Vec<(IssueKind, String)> = issues
+        .into_iter()
+        .map(|issue| (issue.kind, issue.path.replace(&path_album, "")))
+        .collect();
+    kinds.sort_by_key(|(_, path)| path.clone());
+    assert_eq!(kinds, vec![
+        (IssueKind::MissingArtwork, String::new()),
+        (IssueKind::EmptyFile, String::from("1 - New Name.mp3")),
+        (IssueKind::MissingTrack, String::from("2 - Missing.mp3")),
+        (IssueKind::ExtraFile, String::from("3 - Bonus.mp3")),
+    ]);
+}
+
+#[test]
+fn audit_reports_unreadable_files_and_goes_on() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    album.tracks.get_mut("1").unwrap().duration = Some(String::from("not a duration"));
+    album.tracks.insert(String::from("2"), make_track("Missing"));
+    let path_album = get_path_album(&path_library, &album);
+    create_dir_all(&path_album).unwrap();
+    write(format!("{}1 - New Name.mp3", path_album), b"not audio").unwrap();
+
+    let library = Library { albums: vec![album] };
+    let issues = audit_library(&make_config(false), &path_library, &library, 5.0).unwrap();
+    let kinds: Vec<IssueKind> = issues.iter().map(|issue| issue.kind).collect();
+    assert!(kinds.contains(&IssueKind::MissingTrack));
+    assert!(kinds.contains(&IssueKind::Undecodable) || kinds.contains(&IssueKind::Duration));
+}
+
+#[test]
+fn waveforms_are_named_by_padded_position() {
+    let dir = make_temp_dir();
+    let path_library = get_path(&dir);
+    let mut album = make_album_with_cover();
+    album.tracks.get_mut("1").unwrap().wave = Some(Wave { length: 4, points: vec![0, 128, 255, 64] });
+    let path_album = get_path_album(&path_library, &album);
+    create_dir_all(&path_album).unwrap();
+
+    let library = Library { albums: vec![album] };
+    render_library_waves(&make_config(false), &path_library, &library, &RenderOptions::default(), false).unwrap();
+    assert!(Path::new(&format!("{}01.waveform.svg", path_album)).exists());
+}